@@ -0,0 +1,129 @@
+// compares the searches that find a literal query in the whole buffer with
+// the ones that go through every line, like they did before:
+// - the search function with contents.lines().filter(|line| line.contains(query))
+// - the search the command line runs, which skips the lines before a hit, with
+//   reading, decoding and searching every line
+//
+// cargo bench
+//
+// the input is a made up log where few lines match, which is what the
+// skipping is for; when every line matches there is nothing to skip, and both
+// take about as long; each case reports the best of a few runs
+
+use std::hint::black_box;
+use std::io::{BufRead, BufReader};
+use std::time::{Duration, Instant};
+
+use chapter_12::decode::Decoder;
+use chapter_12::matcher::Matcher;
+use chapter_12::{search, Searcher, SinkLine};
+
+const RUNS: usize = 10;
+const QUERIES: [&str; 5] = ["connection reset", "ERROR", "worker-15", "served in", "zzz"];
+
+// what the command line did before: read, decode and search every line,
+// after checking it for the NUL byte of a binary input
+fn search_lines<R: BufRead>(matcher: &Matcher, mut reader: R) -> usize {
+	let mut count = 0;
+	let mut buffer = Vec::new();
+	loop {
+		buffer.clear();
+		if reader.read_until(b'\n', &mut buffer).unwrap() == 0 || buffer.contains(&0) {
+			return count;
+		}
+		let decoded = String::from_utf8_lossy(&buffer);
+		let line = decoded.strip_suffix('\n').unwrap_or(&decoded);
+		if matcher.is_match(line) {
+			count += 1;
+		}
+	}
+}
+
+fn log(lines: usize) -> String {
+	let levels = ["INFO", "DEBUG", "INFO", "WARN"];
+	let mut log = String::new();
+	for i in 0..lines {
+		let level = levels[i % levels.len()];
+		log.push_str(&format!(
+			"2024-01-{:02}T12:{:02}:{:02}Z {} request {} served in {}ms by worker-{}\n",
+			i % 28 + 1,
+			i % 60,
+			(i * 7) % 60,
+			level,
+			i,
+			(i * 13) % 500,
+			i % 16
+		));
+		// a rare line the queries below look for
+		if i % 5000 == 0 {
+			log.push_str("2024-01-01T00:00:00Z ERROR connection reset by peer\n");
+		}
+	}
+	log
+}
+
+fn best_of<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+	let mut best = Duration::MAX;
+	let mut found = 0;
+	for _ in 0..RUNS {
+		let start = Instant::now();
+		found = black_box(f());
+		best = best.min(start.elapsed());
+	}
+	(best, found)
+}
+
+fn main() {
+	let contents = log(200_000);
+	let megabytes = contents.len() as f64 / (1024.0 * 1024.0);
+	println!("searching {:.1} MiB, best of {} runs", megabytes, RUNS);
+
+	println!();
+	println!(
+		"{:<26} {:>12} {:>12} {:>8} {:>8}",
+		"query", "lines filter", "search", "speedup", "lines"
+	);
+	for query in QUERIES {
+		let (lines, expected) = best_of(|| {
+			black_box(&contents)
+				.lines()
+				.filter(|line| line.contains(query))
+				.count()
+		});
+		let (buffer, found) = best_of(|| search(query, black_box(&contents)).len());
+		assert_eq!(expected, found, "both searches find the same lines");
+		report(query, lines, buffer, found);
+	}
+
+	println!();
+	println!(
+		"{:<26} {:>12} {:>12} {:>8} {:>8}",
+		"query", "every line", "skipping", "speedup", "lines"
+	);
+	for query in QUERIES {
+		// both read through a buffered reader, like the command line reads files
+		let searcher = Searcher::builder().build(&[query]).unwrap();
+		let (lines, expected) = best_of(|| {
+			let reader = Decoder::new(BufReader::new(black_box(contents.as_bytes()))).unwrap();
+			search_lines(searcher.matcher(), reader)
+		});
+		let (buffer, found) = best_of(|| {
+			searcher
+				.search_reader(black_box(contents.as_bytes()), &mut |_: &SinkLine| Ok(true))
+				.unwrap()
+		});
+		assert_eq!(expected, found, "both searches find the same lines");
+		report(query, lines, buffer, found);
+	}
+}
+
+fn report(query: &str, lines: Duration, buffer: Duration, found: usize) {
+	println!(
+		"{:<26} {:>10.2}ms {:>10.2}ms {:>7.1}x {:>8}",
+		format!("{:?}", query),
+		lines.as_secs_f64() * 1000.0,
+		buffer.as_secs_f64() * 1000.0,
+		lines.as_secs_f64() / buffer.as_secs_f64(),
+		found
+	);
+}
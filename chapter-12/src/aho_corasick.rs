@@ -0,0 +1,212 @@
+// searching for many literal patterns at once with an aho-corasick automaton
+//
+// the patterns are stored in a trie, where every node also links to the longest
+// proper suffix of its path that is in the trie too (its failure link)
+// the text is then read a single time, one character after the other: on a
+// character without a transition the automaton follows failure links instead of
+// going back in the text, so the work doesn't grow with the number of patterns
+//
+// matches are leftmost-longest: of the matches that start first, the longest one wins
+// for case insensitive search both the patterns and the text are case folded,
+// one character at a time like casefold::find does
+
+use std::collections::VecDeque;
+
+use crate::casefold;
+
+#[derive(Debug, Clone)]
+pub struct AhoCorasick {
+	states: Vec<State>,
+	// the length in (folded) characters of the longest pattern
+	max_len: usize,
+	// whether one of the patterns is empty, so it matches everywhere
+	has_empty: bool,
+	ignore_case: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct State {
+	// sorted by character, for binary search
+	next: Vec<(char, usize)>,
+	fail: usize,
+	// the length of every pattern that ends here, including through failure links,
+	// longest first
+	outputs: Vec<usize>,
+}
+
+impl AhoCorasick {
+	pub fn new<S: AsRef<str>>(patterns: &[S], ignore_case: bool) -> AhoCorasick {
+		let mut states = vec![State::default()];
+		let mut max_len = 0;
+		let mut has_empty = false;
+
+		for pattern in patterns {
+			let pattern = pattern.as_ref();
+			let chars: Vec<char> = if ignore_case {
+				pattern.chars().flat_map(casefold::fold).collect()
+			} else {
+				pattern.chars().collect()
+			};
+			if chars.is_empty() {
+				has_empty = true;
+				continue;
+			}
+			max_len = max_len.max(chars.len());
+
+			let mut state = 0;
+			for &c in &chars {
+				state = match find_next(&states[state], c) {
+					Some(next) => next,
+					None => {
+						states.push(State::default());
+						let next = states.len() - 1;
+						let transitions = &mut states[state].next;
+						let at = transitions.partition_point(|&(t, _)| t < c);
+						transitions.insert(at, (c, next));
+						next
+					}
+				};
+			}
+			if !states[state].outputs.contains(&chars.len()) {
+				states[state].outputs.push(chars.len());
+			}
+		}
+
+		// failure links, breadth first so a state's link is done before its children's
+		let mut queue: VecDeque<usize> = states[0].next.iter().map(|&(_, s)| s).collect();
+		while let Some(state) = queue.pop_front() {
+			let fail = states[state].fail;
+			let inherited = states[fail].outputs.clone();
+			states[state].outputs.extend(inherited);
+			states[state].outputs.sort_unstable_by(|a, b| b.cmp(a));
+
+			for (c, child) in states[state].next.clone() {
+				let mut link = fail;
+				let child_fail = loop {
+					if let Some(next) = find_next(&states[link], c) {
+						break next;
+					}
+					if link == 0 {
+						break 0;
+					}
+					link = states[link].fail;
+				};
+				states[child].fail = child_fail;
+				queue.push_back(child);
+			}
+		}
+
+		AhoCorasick {
+			states,
+			max_len,
+			has_empty,
+			ignore_case,
+		}
+	}
+
+	// the byte range of the leftmost-longest match in `text` at or after `start`
+	pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+		// the byte offset of every folded character read so far, if it is the first
+		// one of its original character, because a match can't start inside a character
+		let mut starts: Vec<Option<usize>> = Vec::new();
+		// the index into `starts` and the byte range of the best match yet
+		let mut best: Option<(usize, usize, usize)> = None;
+		let mut state = 0;
+
+		for (i, c) in text[start..].char_indices() {
+			// no match that starts later can beat the best one anymore
+			if matches!(best, Some((first, _, _)) if starts.len() >= first + self.max_len) {
+				break;
+			}
+			let offset = start + i;
+
+			let mut folded = ['\0'; 3];
+			let mut len = 0;
+			if self.ignore_case {
+				for f in casefold::fold(c) {
+					folded[len] = f;
+					len += 1;
+				}
+			} else {
+				folded[0] = c;
+				len = 1;
+			}
+			for (j, &f) in folded[..len].iter().enumerate() {
+				starts.push((j == 0).then_some(offset));
+				state = self.step(state, f);
+			}
+
+			// and it can't end inside one either, so only whole characters are checked
+			let end = offset + c.len_utf8();
+			let found = self.states[state].outputs.iter().find_map(|&len| {
+				let first = starts.len() - len;
+				starts[first].map(|s| (first, s, end))
+			});
+			if let Some(found) = found {
+				if best.is_none_or(|best| found.0 <= best.0) {
+					best = Some(found);
+				}
+			}
+		}
+
+		match best {
+			Some((_, s, e)) if !(self.has_empty && s > start) => Some((s, e)),
+			_ if self.has_empty => Some((start, start)),
+			_ => None,
+		}
+	}
+
+	fn step(&self, mut state: usize, c: char) -> usize {
+		loop {
+			if let Some(next) = find_next(&self.states[state], c) {
+				return next;
+			}
+			if state == 0 {
+				return 0;
+			}
+			state = self.states[state].fail;
+		}
+	}
+}
+
+fn find_next(state: &State, c: char) -> Option<usize> {
+	state
+		.next
+		.binary_search_by_key(&c, |&(t, _)| t)
+		.ok()
+		.map(|i| state.next[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn find(patterns: &[&str], ignore_case: bool, text: &str) -> Option<(usize, usize)> {
+		AhoCorasick::new(patterns, ignore_case).find_at(text, 0)
+	}
+
+	#[test]
+	fn leftmost_longest() {
+		let patterns = ["he", "she", "hers", "his"];
+		assert_eq!(Some((1, 4)), find(&patterns, false, "ushers"));
+		assert_eq!(Some((0, 4)), find(&patterns, false, "hers"));
+		assert_eq!(Some((2, 5)), find(&patterns, false, "a his"));
+		assert_eq!(None, find(&patterns, false, "nothing"));
+		assert_eq!(
+			Some((0, 6)),
+			find(&["abcd", "b", "abcdef"], false, "abcdefg")
+		);
+		assert_eq!(Some((1, 2)), find(&["b", "bcdx"], false, "abcde"));
+
+		let ac = AhoCorasick::new(&patterns, false);
+		assert_eq!(Some((7, 9)), ac.find_at("she is he", 3));
+	}
+
+	#[test]
+	fn ignore_case() {
+		let patterns = ["straße", "ERROR"];
+		assert_eq!(Some((4, 11)), find(&patterns, true, "the STRASSE"));
+		assert_eq!(Some((0, 5)), find(&patterns, true, "error!"));
+		assert_eq!(None, find(&["s"], true, "ß"));
+	}
+}
@@ -0,0 +1,885 @@
+// command line parsing for minigrep
+//
+// settings come from three places, and later ones win:
+// 1. the config file, named by MINIGREP_CONFIG or else ~/.minigreprc if it exists,
+//    which holds one flag per line, with '#' starting a comment line
+// 2. the environment: IGNORE_CASE turns on case insensitive search
+// 3. the flags on the command line
+//
+// short flags can be combined (-inv), and take their value either glued (-A3)
+// or as the next argument (-A 3); long flags take it as --name=value or --name value
+// everything after "--" is positional, even if it starts with a dash
+//
+// `minigrep index [DIR...]` builds the index that --index searches use, instead of searching,
+// so searching for the word "index" needs -e index or -- index
+//
+// the pattern is the first positional argument, unless patterns are given with
+// -e or -f, which can be repeated and then make every positional argument a file
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::error::SearchError;
+use crate::printer::{BinaryFiles, ColorChoice, OutputMode};
+use crate::replace::Template;
+use crate::searcher::{Case, Searcher};
+use crate::walk::WalkOptions;
+use crate::Config;
+
+// a flag that minigrep understands
+struct Flag {
+	short: Option<char>,
+	long: &'static str,
+	// the name of the value the flag takes, shown in the help
+	value: Option<&'static str>,
+	help: &'static str,
+}
+
+const FLAGS: &[Flag] = &[
+	Flag {
+		short: Some('i'),
+		long: "ignore-case",
+		value: None,
+		help: "match regardless of case",
+	},
+	Flag {
+		short: Some('s'),
+		long: "case-sensitive",
+		value: None,
+		help: "match case exactly, even if IGNORE_CASE is set",
+	},
+	Flag {
+		short: Some('S'),
+		long: "smart-case",
+		value: None,
+		help: "ignore case only if PATTERN is all lowercase",
+	},
+	Flag {
+		short: Some('e'),
+		long: "regexp",
+		value: Some("PATTERN"),
+		help: "search for PATTERN, which may be given more than once",
+	},
+	Flag {
+		short: Some('f'),
+		long: "file",
+		value: Some("FILE"),
+		help: "search for the patterns in FILE, one per line",
+	},
+	Flag {
+		short: None,
+		long: "regex",
+		value: None,
+		help: "treat PATTERN as a regular expression",
+	},
+	Flag {
+		short: None,
+		long: "fuzzy",
+		value: Some("K"),
+		help: "match substrings within K typos of PATTERN, the closest of overlapping ones, printing their distance=N",
+	},
+	Flag {
+		short: Some('U'),
+		long: "multiline",
+		value: None,
+		help:
+			"let PATTERN match across lines, with \\n for a newline, printing every line it spans",
+	},
+	Flag {
+		short: Some('w'),
+		long: "word-regexp",
+		value: None,
+		help: "only match whole words",
+	},
+	Flag {
+		short: Some('x'),
+		long: "line-regexp",
+		value: None,
+		help: "only match whole lines",
+	},
+	Flag {
+		short: Some('v'),
+		long: "invert-match",
+		value: None,
+		help: "select the lines that don't match",
+	},
+	Flag {
+		short: Some('c'),
+		long: "count",
+		value: None,
+		help: "print the number of selected lines of each file",
+	},
+	Flag {
+		short: Some('l'),
+		long: "files-with-matches",
+		value: None,
+		help: "print the name of each file with a selected line",
+	},
+	Flag {
+		short: Some('L'),
+		long: "files-without-match",
+		value: None,
+		help: "print the name of each file without a selected line",
+	},
+	Flag {
+		short: Some('q'),
+		long: "quiet",
+		value: None,
+		help: "print nothing, only set the exit status",
+	},
+	Flag {
+		short: None,
+		long: "json",
+		value: None,
+		help: "print every result as a json object, one per line",
+	},
+	Flag {
+		short: Some('m'),
+		long: "max-count",
+		value: Some("NUM"),
+		help: "stop reading a file after NUM selected lines",
+	},
+	Flag {
+		short: Some('A'),
+		long: "after-context",
+		value: Some("NUM"),
+		help: "print NUM lines after each match",
+	},
+	Flag {
+		short: Some('B'),
+		long: "before-context",
+		value: Some("NUM"),
+		help: "print NUM lines before each match",
+	},
+	Flag {
+		short: Some('C'),
+		long: "context",
+		value: Some("NUM"),
+		help: "print NUM lines before and after each match",
+	},
+	Flag {
+		short: Some('n'),
+		long: "line-number",
+		value: None,
+		help: "prefix lines with their line number",
+	},
+	Flag {
+		short: None,
+		long: "column",
+		value: None,
+		help: "prefix lines with the column of the first match",
+	},
+	Flag {
+		short: None,
+		long: "color",
+		value: Some("WHEN"),
+		help: "highlight matches: auto (the default), always or never",
+	},
+	Flag {
+		short: Some('a'),
+		long: "text",
+		value: None,
+		help: "search binary files as if they were text",
+	},
+	Flag {
+		short: None,
+		long: "binary-files",
+		value: Some("TYPE"),
+		help: "binary files: binary (say if they match, the default), text or without-match",
+	},
+	Flag {
+		short: None,
+		long: "replace",
+		value: Some("TEMPLATE"),
+		help:
+			"show a diff replacing matches with TEMPLATE, where $0 is the match and $1.. its groups",
+	},
+	Flag {
+		short: None,
+		long: "in-place",
+		value: None,
+		help: "with --replace, write the replacements to the files instead",
+	},
+	Flag {
+		short: None,
+		long: "backup",
+		value: None,
+		help: "with --in-place, keep the original of every changed FILE as FILE.bak",
+	},
+	Flag {
+		short: None,
+		long: "watch",
+		value: None,
+		help: "keep printing the matching lines appended to each FILE, like tail -f",
+	},
+	Flag {
+		short: None,
+		long: "index",
+		value: None,
+		help: "search directories through the index built by 'minigrep index DIR'",
+	},
+	Flag {
+		short: None,
+		long: "hidden",
+		value: None,
+		help: "also search hidden files and directories",
+	},
+	Flag {
+		short: None,
+		long: "no-ignore",
+		value: None,
+		help: "don't skip the files that .gitignore, .ignore and .minigrepignore ignore",
+	},
+	Flag {
+		short: Some('g'),
+		long: "glob",
+		value: Some("GLOB"),
+		help: "only search the files in directories that match GLOB, or don't with !GLOB",
+	},
+	Flag {
+		short: Some('t'),
+		long: "type",
+		value: Some("TYPE"),
+		help: "only search the files in directories of TYPE, like rust or toml",
+	},
+	Flag {
+		short: None,
+		long: "type-list",
+		value: None,
+		help: "print the types --type knows and exit",
+	},
+	Flag {
+		short: Some('j'),
+		long: "threads",
+		value: Some("NUM"),
+		help: "search NUM files at the same time",
+	},
+	Flag {
+		short: Some('h'),
+		long: "help",
+		value: None,
+		help: "print this help and exit",
+	},
+	Flag {
+		short: Some('V'),
+		long: "version",
+		value: None,
+		help: "print the version and exit",
+	},
+];
+
+// what the command line asks for
+pub enum Command {
+	Search(Config),
+	// build or update the index of these directories
+	Index {
+		dirs: Vec<String>,
+		walk: WalkOptions,
+	},
+	Help,
+	Version,
+	TypeList,
+}
+
+// the settings before the pattern is compiled
+struct Settings {
+	case: Case,
+	use_regex: bool,
+	fuzzy: Option<usize>,
+	multiline: bool,
+	whole_word: bool,
+	whole_line: bool,
+	invert: bool,
+	mode: OutputMode,
+	max_count: Option<usize>,
+	before_context: usize,
+	after_context: usize,
+	line_number: bool,
+	column: bool,
+	color: ColorChoice,
+	binary_files: BinaryFiles,
+	replace: Option<String>,
+	in_place: bool,
+	backup: bool,
+	watch: bool,
+	index: bool,
+	walk: WalkOptions,
+	threads: usize,
+	help: bool,
+	version: bool,
+	type_list: bool,
+	// the patterns from -e and -f, None if there were none
+	patterns: Option<Vec<String>>,
+	positional: Vec<String>,
+}
+
+impl Default for Settings {
+	fn default() -> Settings {
+		Settings {
+			case: Case::Sensitive,
+			use_regex: false,
+			fuzzy: None,
+			multiline: false,
+			whole_word: false,
+			whole_line: false,
+			invert: false,
+			mode: OutputMode::Lines,
+			max_count: None,
+			before_context: 0,
+			after_context: 0,
+			line_number: false,
+			column: false,
+			color: ColorChoice::Auto,
+			binary_files: BinaryFiles::Binary,
+			replace: None,
+			in_place: false,
+			backup: false,
+			watch: false,
+			index: false,
+			walk: WalkOptions::default(),
+			threads: thread::available_parallelism().map_or(1, |n| n.get()),
+			help: false,
+			version: false,
+			type_list: false,
+			patterns: None,
+			positional: Vec::new(),
+		}
+	}
+}
+
+impl Settings {
+	fn apply(&mut self, flag: &Flag, value: Option<String>) -> Result<(), SearchError> {
+		let value = value.unwrap_or_default();
+		match flag.long {
+			"ignore-case" => self.case = Case::Insensitive,
+			"case-sensitive" => self.case = Case::Sensitive,
+			"smart-case" => self.case = Case::Smart,
+			"regexp" => self.patterns.get_or_insert_with(Vec::new).push(value),
+			"file" => {
+				let contents =
+					fs::read_to_string(&value).map_err(|source| SearchError::ArgumentFile {
+						argument: format!("--{}", flag.long),
+						path: PathBuf::from(&value),
+						source,
+					})?;
+				self.patterns
+					.get_or_insert_with(Vec::new)
+					.extend(contents.lines().map(String::from));
+			}
+			"regex" => self.use_regex = true,
+			"fuzzy" => self.fuzzy = Some(parse_number(flag, &value)?),
+			"multiline" => self.multiline = true,
+			"word-regexp" => self.whole_word = true,
+			"line-regexp" => self.whole_line = true,
+			"invert-match" => self.invert = true,
+			"count" => self.mode = OutputMode::Count,
+			"files-with-matches" => self.mode = OutputMode::FilesWithMatches,
+			"files-without-match" => self.mode = OutputMode::FilesWithoutMatch,
+			"quiet" => self.mode = OutputMode::Quiet,
+			"json" => self.mode = OutputMode::Json,
+			"max-count" => self.max_count = Some(parse_number(flag, &value)?),
+			"after-context" => self.after_context = parse_number(flag, &value)?,
+			"before-context" => self.before_context = parse_number(flag, &value)?,
+			"context" => {
+				self.after_context = parse_number(flag, &value)?;
+				self.before_context = self.after_context;
+			}
+			"line-number" => self.line_number = true,
+			"column" => self.column = true,
+			"color" => {
+				self.color = ColorChoice::parse(&value)
+					.ok_or_else(|| invalid_value(flag, &value, "auto, always or never"))?
+			}
+			"text" => self.binary_files = BinaryFiles::Text,
+			"binary-files" => {
+				self.binary_files = BinaryFiles::parse(&value)
+					.ok_or_else(|| invalid_value(flag, &value, "binary, text or without-match"))?
+			}
+			"replace" => self.replace = Some(value),
+			"in-place" => self.in_place = true,
+			"backup" => self.backup = true,
+			"watch" => self.watch = true,
+			"index" => self.index = true,
+			"hidden" => self.walk.hidden = true,
+			"no-ignore" => self.walk.no_ignore = true,
+			"glob" => self.walk.filter.add_glob(&value)?,
+			"type" => self.walk.filter.add_type(&value)?,
+			"type-list" => self.type_list = true,
+			"threads" => self.threads = parse_number(flag, &value)?.max(1),
+			"help" => self.help = true,
+			"version" => self.version = true,
+			_ => unreachable!("every flag in FLAGS is handled"),
+		}
+		Ok(())
+	}
+
+	// reads the arguments, without the program name
+	fn parse_args(&mut self, args: &[String]) -> Result<(), SearchError> {
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			if arg == "--" {
+				self.positional.extend(args.by_ref().cloned());
+			} else if let Some(long) = arg.strip_prefix("--") {
+				let (name, glued) = match long.split_once('=') {
+					Some((name, value)) => (name, Some(value.to_string())),
+					None => (long, None),
+				};
+				let flag = FLAGS
+					.iter()
+					.find(|flag| flag.long == name)
+					.ok_or_else(|| SearchError::UnknownOption(format!("--{}", name)))?;
+				let value = match (flag.value, glued) {
+					(None, Some(_)) => {
+						return Err(SearchError::UnexpectedValue(format!("--{}", name)));
+					}
+					(None, None) => None,
+					(Some(_), Some(value)) => Some(value),
+					(Some(_), None) => Some(next_value(flag, args.next())?),
+				};
+				self.apply(flag, value)?;
+			} else if arg.len() > 1 && arg.starts_with('-') {
+				// a cluster of short flags, where a flag taking a value ends the cluster
+				let cluster = &arg[1..];
+				for (i, c) in cluster.char_indices() {
+					let flag = FLAGS
+						.iter()
+						.find(|flag| flag.short == Some(c))
+						.ok_or_else(|| SearchError::UnknownOption(format!("-{}", c)))?;
+					if flag.value.is_none() {
+						self.apply(flag, None)?;
+						continue;
+					}
+					let rest = &cluster[i + c.len_utf8()..];
+					let value = if rest.is_empty() {
+						next_value(flag, args.next())?
+					} else {
+						rest.to_string()
+					};
+					self.apply(flag, Some(value))?;
+					break;
+				}
+			} else {
+				// a lone "-" is standard input
+				self.positional.push(arg.clone());
+			}
+		}
+		Ok(())
+	}
+
+	fn into_config(self) -> Result<Config, SearchError> {
+		let (patterns, filenames) = match self.patterns {
+			Some(patterns) => (patterns, self.positional),
+			None => {
+				let mut positional = self.positional.into_iter();
+				let pattern = positional.next().ok_or(SearchError::MissingPattern)?;
+				(vec![pattern], positional.collect())
+			}
+		};
+
+		if self.in_place && self.replace.is_none() {
+			return Err(SearchError::invalid("--in-place", "needs --replace"));
+		}
+		if self.backup && !self.in_place {
+			return Err(SearchError::invalid("--backup", "needs --in-place"));
+		}
+		if self.replace.is_some() && self.invert {
+			return Err(SearchError::invalid(
+				"--replace",
+				"can't be combined with --invert-match",
+			));
+		}
+		if self.replace.is_some() && self.multiline {
+			return Err(SearchError::invalid(
+				"--replace",
+				"rewrites line by line, so it can't be combined with --multiline",
+			));
+		}
+		let stdin = filenames.is_empty() || filenames.iter().any(|name| name == "-");
+		if self.in_place && stdin {
+			return Err(SearchError::invalid(
+				"--in-place",
+				"can't rewrite standard input",
+			));
+		}
+		if self.watch {
+			if stdin {
+				return Err(SearchError::invalid(
+					"--watch",
+					"can't watch standard input",
+				));
+			}
+			if self.replace.is_some() {
+				return Err(SearchError::invalid(
+					"--watch",
+					"can't be combined with --replace",
+				));
+			}
+			if self.multiline {
+				return Err(SearchError::invalid(
+					"--watch",
+					"only sees the lines appended since the last look, so it can't be combined with --multiline",
+				));
+			}
+			if !matches!(self.mode, OutputMode::Lines | OutputMode::Json) {
+				return Err(SearchError::invalid(
+					"--watch",
+					"only prints lines, so it can't be combined with -c, -l, -L or -q",
+				));
+			}
+		}
+
+		// compile the patterns once, so a bad pattern is reported before reading any file
+		let matcher = Searcher::builder()
+			.case(self.case)
+			.regex(self.use_regex)
+			.fuzzy(self.fuzzy)
+			.multiline(self.multiline)
+			.word(self.whole_word)
+			.line(self.whole_line)
+			.matcher(&patterns)?;
+
+		Ok(Config {
+			patterns,
+			filenames,
+			matcher,
+			invert: self.invert,
+			mode: self.mode,
+			max_count: self.max_count,
+			before_context: self.before_context,
+			after_context: self.after_context,
+			line_number: self.line_number,
+			column: self.column,
+			color: self.color,
+			binary_files: self.binary_files,
+			multiline: self.multiline,
+			replace: self.replace.as_deref().map(Template::parse),
+			in_place: self.in_place,
+			backup: self.backup,
+			watch: self.watch,
+			index: self.index,
+			walk: self.walk,
+			threads: self.threads,
+		})
+	}
+}
+
+fn next_value(flag: &Flag, value: Option<&String>) -> Result<String, SearchError> {
+	value.cloned().ok_or_else(|| SearchError::MissingValue {
+		option: format!("--{}", flag.long),
+		value: flag.value.unwrap_or("VALUE").to_string(),
+	})
+}
+
+fn parse_number(flag: &Flag, value: &str) -> Result<usize, SearchError> {
+	value
+		.parse()
+		.map_err(|_| invalid_value(flag, value, "a number"))
+}
+
+fn invalid_value(flag: &Flag, value: &str, expected: &str) -> SearchError {
+	SearchError::InvalidValue {
+		option: format!("--{}", flag.long),
+		value: value.to_string(),
+		expected: expected.to_string(),
+	}
+}
+
+// parses the command line, including the program name in args[0],
+// together with the config file and the environment
+pub fn parse(args: &[String]) -> Result<Command, SearchError> {
+	let (file, file_args) = match config_file() {
+		Some(path) => {
+			let file_args = read_config_file(&path)?;
+			(path, file_args)
+		}
+		None => (PathBuf::new(), Vec::new()),
+	};
+	// is_ok returns false if the environment variable isn't set
+	let env_ignore_case = env::var("IGNORE_CASE").is_ok();
+
+	parse_from(
+		&file,
+		&file_args,
+		env_ignore_case,
+		args.get(1..).unwrap_or_default(),
+	)
+}
+
+// `file` is where `file_args` come from, for errors
+fn parse_from(
+	file: &Path,
+	file_args: &[String],
+	env_ignore_case: bool,
+	args: &[String],
+) -> Result<Command, SearchError> {
+	let mut settings = Settings::default();
+
+	let in_file = |e: SearchError| SearchError::ConfigFile {
+		path: file.to_path_buf(),
+		source: Box::new(e),
+	};
+	settings.parse_args(file_args).map_err(in_file)?;
+	if let Some(first) = settings.positional.first() {
+		return Err(in_file(SearchError::invalid(
+			first,
+			"isn't a flag, and only flags are allowed",
+		)));
+	}
+	if settings.patterns.is_some() {
+		return Err(in_file(SearchError::invalid(
+			"-e and -f",
+			"aren't allowed, only flags are",
+		)));
+	}
+
+	if env_ignore_case {
+		settings.case = Case::Insensitive;
+	}
+
+	let (args, index) = match args.split_first() {
+		Some((first, rest)) if first == "index" => (rest, true),
+		_ => (args, false),
+	};
+	settings.parse_args(args)?;
+
+	if settings.help {
+		Ok(Command::Help)
+	} else if settings.version {
+		Ok(Command::Version)
+	} else if settings.type_list {
+		Ok(Command::TypeList)
+	} else if index {
+		let dirs = match settings.positional.is_empty() {
+			true => vec![String::from(".")],
+			false => settings.positional,
+		};
+		Ok(Command::Index {
+			dirs,
+			walk: settings.walk,
+		})
+	} else {
+		settings.into_config().map(Command::Search)
+	}
+}
+
+fn config_file() -> Option<PathBuf> {
+	if let Some(path) = env::var_os("MINIGREP_CONFIG") {
+		return Some(PathBuf::from(path));
+	}
+	let path = PathBuf::from(env::var_os("HOME")?).join(".minigreprc");
+	path.is_file().then_some(path)
+}
+
+fn read_config_file(path: &Path) -> Result<Vec<String>, SearchError> {
+	let contents = fs::read_to_string(path).map_err(|e| SearchError::ConfigFile {
+		path: path.to_path_buf(),
+		source: Box::new(e),
+	})?;
+	Ok(contents
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(String::from)
+		.collect())
+}
+
+pub fn help() -> String {
+	let mut help = String::from(
+		"Usage: minigrep [OPTIONS] PATTERN [FILE...]\n\
+		 \x20      minigrep [OPTIONS] -e PATTERN... [-f FILE...] [FILE...]\n\
+		 \x20      minigrep index [--hidden] [--no-ignore] [DIR...]\n\
+		 \n\
+		 Search for PATTERN in each FILE. Directories are searched recursively,\n\
+		 and without a FILE, or with '-', standard input is searched.\n\
+		 'minigrep index' builds an index of each DIR (by default '.') for --index.\n\
+		 \n\
+		 Options:\n",
+	);
+	for flag in FLAGS {
+		let short = flag
+			.short
+			.map_or(String::from("    "), |c| format!("-{}, ", c));
+		let long = match flag.value {
+			Some(value) => format!("--{} {}", flag.long, value),
+			None => format!("--{}", flag.long),
+		};
+		help.push_str(&format!("  {}{:<26}{}\n", short, long, flag.help));
+	}
+	help.push_str(
+		"\nSettings are read from the config file (MINIGREP_CONFIG or ~/.minigreprc),\n\
+		 then from the environment (IGNORE_CASE), then from the command line,\n\
+		 and later ones take precedence.\n\
+		 \n\
+		 The exit status is 0 if a line was selected and 1 if none was, like grep.\n\
+		 Errors exit with 2 or more, and each kind with its own status:\n\
+		 2 for bad arguments, 3 for a file named by an argument that can't be read,\n\
+		 4 for a bad config file, 5 for an invalid pattern, 6 for inputs that couldn't\n\
+		 be searched, 7 for an index that couldn't be built and 8 for output that\n\
+		 couldn't be written.\n",
+	);
+	help
+}
+
+pub fn version() -> String {
+	format!("minigrep {}", env!("CARGO_PKG_VERSION"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(args: &[&str]) -> Vec<String> {
+		args.iter().map(|arg| arg.to_string()).collect()
+	}
+
+	fn config(file_args: &[&str], env_ignore_case: bool, cli: &[&str]) -> Config {
+		match parse_from(
+			Path::new("minigreprc"),
+			&args(file_args),
+			env_ignore_case,
+			&args(cli),
+		) {
+			Ok(Command::Search(config)) => config,
+			Ok(_) => panic!("expected a search"),
+			Err(e) => panic!("{}", e),
+		}
+	}
+
+	#[test]
+	fn combined_short_flags_and_values() {
+		let config = config(&[], false, &["-inA3", "-j", "2", "query", "a", "b"]);
+		assert!(config.matcher.is_match("QUERY"));
+		assert!(config.line_number);
+		assert_eq!(3, config.after_context);
+		assert_eq!(2, config.threads);
+		assert_eq!(vec!["query"], config.patterns);
+		assert_eq!(vec!["a", "b"], config.filenames);
+	}
+
+	#[test]
+	fn double_dash_ends_the_flags() {
+		let config = config(&[], false, &["--max-count=1", "--", "-v", "-"]);
+		assert_eq!(Some(1), config.max_count);
+		assert!(!config.invert);
+		assert_eq!(vec!["-v"], config.patterns);
+		assert_eq!(vec!["-"], config.filenames);
+	}
+
+	#[test]
+	fn flags_override_environment_which_overrides_config_file() {
+		assert!(config(&["--case-sensitive"], true, &["q"])
+			.matcher
+			.is_match("Q"));
+		assert!(!config(&[], true, &["-s", "q"]).matcher.is_match("Q"));
+		assert_eq!(2, config(&["-C", "2"], false, &["q"]).before_context);
+		assert_eq!(1, config(&["-C", "2"], false, &["-B1", "q"]).before_context);
+	}
+
+	#[test]
+	fn smart_case() {
+		let matches = |cli: &[&str], text: &str| config(&[], false, cli).matcher.is_match(text);
+		assert!(matches(&["-S", "error"], "ERROR"));
+		assert!(!matches(&["-S", "Error"], "ERROR"));
+		// escapes like \W aren't uppercase letters, but an escaped backslash is no escape
+		assert!(matches(&["-S", "--regex", r"foo\Wbar\S"], "FOO BARX"));
+		assert!(!matches(&["-S", "--regex", r"foo\\Wbar"], r"FOO\WBAR"));
+		assert!(!matches(&["-S", r"foo\Wbar"], r"FOO\WBAR"));
+	}
+
+	#[test]
+	fn help_and_version() {
+		assert!(matches!(
+			parse_from(Path::new("minigreprc"), &[], false, &args(&["--help"])),
+			Ok(Command::Help)
+		));
+		assert!(matches!(
+			parse_from(Path::new("minigreprc"), &[], false, &args(&["-V"])),
+			Ok(Command::Version)
+		));
+	}
+
+	#[test]
+	fn index_is_a_subcommand() {
+		let command = parse_from(
+			Path::new("minigreprc"),
+			&[],
+			false,
+			&args(&["index", "--hidden", "src"]),
+		);
+		assert!(
+			matches!(command, Ok(Command::Index { dirs, walk }) if dirs == ["src"] && walk.hidden)
+		);
+		// after "--" it is the pattern again
+		let search = config(&[], false, &["--", "index", "src"]);
+		assert_eq!(vec!["index"], search.patterns);
+		assert_eq!(vec!["src"], search.filenames);
+	}
+
+	#[test]
+	fn several_patterns() {
+		let file = env::temp_dir().join(format!("minigrep-patterns-{}", std::process::id()));
+		fs::write(&file, "blocked-1\nblocked-2\n").unwrap();
+		let file = file.display().to_string();
+		let literal = config(&[], false, &["-e", "one", "-f", &file, "-etwo", "a.log"]);
+		let _ = fs::remove_file(&file);
+
+		assert_eq!(
+			vec!["one", "blocked-1", "blocked-2", "two"],
+			literal.patterns
+		);
+		assert_eq!(vec!["a.log"], literal.filenames);
+		assert!(literal.matcher.is_match("user blocked-2 logged in"));
+		assert!(!literal.matcher.is_match("user blocked-3 logged in"));
+
+		let regex = config(&[], false, &["--regex", "-e", "^a+$", "-e", "b|c"]);
+		assert!(regex.matcher.is_match("aaa"));
+		assert!(regex.matcher.is_match("xcx"));
+		assert!(!regex.matcher.is_match("xax"));
+	}
+
+	#[test]
+	fn clear_errors() {
+		let error = |file_args: &[&str], cli: &[&str]| {
+			parse_from(Path::new("minigreprc"), &args(file_args), false, &args(cli))
+				.err()
+				.unwrap()
+		};
+		assert!(matches!(
+			error(&[], &["-F", "q"]),
+			SearchError::UnknownOption(option) if option == "-F"
+		));
+		assert!(matches!(
+			error(&["-m3", "q"], &["x"]),
+			SearchError::ConfigFile { .. }
+		));
+		assert!(matches!(
+			error(&[], &["--regex", "a(", "f"]),
+			SearchError::Pattern { pattern, .. } if pattern == "a("
+		));
+
+		let error = |cli: &[&str]| error(&[], cli).to_string();
+		assert_eq!(
+			"unknown option '--frobnicate'",
+			error(&["--frobnicate", "q"])
+		);
+		assert_eq!("unknown option '-z'", error(&["-nz", "q"]));
+		assert_eq!(
+			"option '--after-context' needs a value (NUM)",
+			error(&["q", "-A"])
+		);
+		assert_eq!(
+			"invalid value 'x' for '--max-count': expected a number",
+			error(&["-m", "x", "q"])
+		);
+		assert_eq!(
+			"option '--count' doesn't take a value",
+			error(&["--count=3", "q"])
+		);
+		assert_eq!(
+			"missing PATTERN to search for (try '--help' for usage)",
+			error(&[])
+		);
+	}
+}
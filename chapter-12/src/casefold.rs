@@ -0,0 +1,160 @@
+// unicode case folding, for comparing text regardless of case
+//
+// folding maps every character to a canonical form, which for most characters is
+// its lowercase form; it differs where lowercasing isn't enough to compare, like
+// ß and ẞ, which fold to "ss" so they match "SS", or the greek final sigma ς,
+// which folds to σ like Σ does
+//
+// characters are folded one at a time while comparing, so no folded copy
+// of the searched text is ever allocated
+
+use std::char::ToLowercase;
+use std::str::Chars;
+
+// the characters whose full case folding isn't their lowercase form
+fn special(c: char) -> Option<&'static str> {
+	let folded = match c {
+		'ß' | 'ẞ' => "ss",
+		'ſ' => "s",
+		'ς' => "σ",
+		'ϐ' => "β",
+		'ϑ' => "θ",
+		'ϕ' => "φ",
+		'ϖ' => "π",
+		'ϰ' => "κ",
+		'ϱ' => "ρ",
+		'ϵ' => "ε",
+		'\u{1fbe}' => "ι",
+		'ẛ' => "ṡ",
+		'ŉ' => "ʼn",
+		'ǰ' => "ǰ",
+		'ΐ' => "ΐ",
+		'ΰ' => "ΰ",
+		'և' => "եւ",
+		'ẖ' => "ẖ",
+		'ẗ' => "ẗ",
+		'ẘ' => "ẘ",
+		'ẙ' => "ẙ",
+		'ẚ' => "aʾ",
+		'ﬀ' => "ff",
+		'ﬁ' => "fi",
+		'ﬂ' => "fl",
+		'ﬃ' => "ffi",
+		'ﬄ' => "ffl",
+		'ﬅ' | 'ﬆ' => "st",
+		_ => return None,
+	};
+	Some(folded)
+}
+
+// the folded form of a single character, which may be several characters long
+pub enum Fold {
+	Lower(ToLowercase),
+	Special(Chars<'static>),
+}
+
+impl Iterator for Fold {
+	type Item = char;
+
+	fn next(&mut self) -> Option<char> {
+		match self {
+			Fold::Lower(chars) => chars.next(),
+			Fold::Special(chars) => chars.next(),
+		}
+	}
+}
+
+pub fn fold(c: char) -> Fold {
+	match special(c) {
+		Some(folded) => Fold::Special(folded.chars()),
+		None => Fold::Lower(c.to_lowercase()),
+	}
+}
+
+pub fn fold_str(s: &str) -> String {
+	s.chars().flat_map(fold).collect()
+}
+
+// whether any letter in `s` is uppercase, which turns smart case into case sensitive search
+pub fn has_uppercase(s: &str) -> bool {
+	s.chars().any(char::is_uppercase)
+}
+
+// like has_uppercase for a regular expression, where the letter after a backslash
+// names a class or an assertion (\W, \S, \B) instead of matching itself
+pub fn regex_has_uppercase(pattern: &str) -> bool {
+	let mut chars = pattern.chars();
+	while let Some(c) = chars.next() {
+		if c == '\\' {
+			chars.next();
+		} else if c.is_uppercase() {
+			return true;
+		}
+	}
+	false
+}
+
+// the byte range of the first match of the already folded `query` in `text`,
+// starting at or after `start`
+// the range refers to `text` itself and always lies on character boundaries
+pub fn find(text: &str, query: &str, start: usize) -> Option<(usize, usize)> {
+	let first = match query.chars().next() {
+		Some(first) => first,
+		None => return Some((start, start)),
+	};
+	text[start..]
+		.char_indices()
+		.map(|(i, c)| (start + i, c))
+		// cheaply skip the characters that can't start a match
+		.filter(|&(_, c)| fold(c).next() == Some(first))
+		.find_map(|(s, _)| match_at(text, query, s).map(|e| (s, e)))
+}
+
+// where a match of the folded `query` starting at byte `start` of `text` ends
+fn match_at(text: &str, query: &str, start: usize) -> Option<usize> {
+	let mut query = query.chars().peekable();
+
+	for (i, c) in text[start..].char_indices() {
+		for folded in fold(c) {
+			if query.next() != Some(folded) {
+				return None;
+			}
+		}
+		// a match can't end in the middle of a character's folding,
+		// so "s" doesn't match half of "ß"
+		if query.peek().is_none() {
+			return Some(start + i + c.len_utf8());
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn find_folded(text: &str, query: &str) -> Option<(usize, usize)> {
+		find(text, &fold_str(query), 0)
+	}
+
+	#[test]
+	fn full_folding() {
+		assert_eq!(Some((0, 7)), find_folded("STRASSE", "straße"));
+		assert_eq!(Some((0, 7)), find_folded("Straße", "STRASSE"));
+		assert_eq!(None, find_folded("ß", "s"));
+		assert_eq!(Some((4, 8)), find_folded("the ﬁle", "FIL"));
+	}
+
+	#[test]
+	fn greek_sigma() {
+		assert_eq!(Some((0, 16)), find_folded("ΟΔΥΣΣΕΥΣ", "οδυσσευς"));
+		assert_eq!(Some((0, 16)), find_folded("οδυσσευς", "ΟΔΥΣΣΕΥΣ"));
+	}
+
+	#[test]
+	fn smart_case() {
+		assert!(!has_uppercase("error"));
+		assert!(has_uppercase("Error"));
+	}
+}
@@ -0,0 +1,192 @@
+// turns the bytes of an input into utf-8 text that can be searched
+//
+// the first bytes of the input decide its encoding, through a byte order mark:
+// - EF BB BF is utf-8, and the mark is dropped
+// - FF FE is utf-16 little endian and FE FF utf-16 big endian, which are
+//   transcoded to utf-8 as they are read
+// anything else is read as it is; bytes that still aren't valid utf-8, like
+// latin-1 text, are replaced with U+FFFD by the printer instead of failing the search
+
+use std::io::{self, BufRead, Read};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Encoding {
+	#[default]
+	Utf8,
+	Utf16Le,
+	Utf16Be,
+}
+
+impl Encoding {
+	// the encoding that the first bytes of an input announce, and the length of the mark
+	pub fn detect(start: &[u8]) -> (Encoding, usize) {
+		if start.starts_with(&[0xEF, 0xBB, 0xBF]) {
+			(Encoding::Utf8, 3)
+		} else if start.starts_with(&[0xFF, 0xFE]) {
+			(Encoding::Utf16Le, 2)
+		} else if start.starts_with(&[0xFE, 0xFF]) {
+			(Encoding::Utf16Be, 2)
+		} else {
+			(Encoding::Utf8, 0)
+		}
+	}
+
+	// `text` in this encoding, without a byte order mark
+	pub fn encode(self, text: &str) -> Vec<u8> {
+		match self {
+			Encoding::Utf8 => text.as_bytes().to_vec(),
+			Encoding::Utf16Le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+			Encoding::Utf16Be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+		}
+	}
+
+	// how a newline is encoded, which in utf-16 starts at an even offset
+	pub fn newline(self) -> &'static [u8] {
+		match self {
+			Encoding::Utf8 => b"\n",
+			Encoding::Utf16Le => b"\n\0",
+			Encoding::Utf16Be => b"\0\n",
+		}
+	}
+}
+
+pub struct Decoder<R> {
+	inner: R,
+	encoding: Encoding,
+	// the transcoded utf-8 that wasn't consumed yet, from `pos` on
+	buffer: Vec<u8>,
+	pos: usize,
+	// the bytes of an incomplete utf-16 unit or surrogate pair
+	pending: Vec<u8>,
+}
+
+impl<R: BufRead> Decoder<R> {
+	pub fn new(mut inner: R) -> io::Result<Decoder<R>> {
+		let (encoding, bom) = Encoding::detect(inner.fill_buf()?);
+		inner.consume(bom);
+		Ok(Decoder::with_encoding(inner, encoding))
+	}
+
+	// for reading on from the middle of an input, whose start told the encoding
+	pub fn with_encoding(inner: R, encoding: Encoding) -> Decoder<R> {
+		Decoder {
+			inner,
+			encoding,
+			buffer: Vec::new(),
+			pos: 0,
+			pending: Vec::new(),
+		}
+	}
+
+	// refills `buffer` with the utf-8 for the next chunk of utf-16 input
+	fn transcode(&mut self) -> io::Result<()> {
+		self.buffer.clear();
+		self.pos = 0;
+
+		while self.buffer.is_empty() {
+			let chunk = self.inner.fill_buf()?;
+			if chunk.is_empty() {
+				// the input ends in the middle of a character
+				if !self.pending.is_empty() {
+					self.pending.clear();
+					push_char(&mut self.buffer, char::REPLACEMENT_CHARACTER);
+				}
+				return Ok(());
+			}
+			self.pending.extend_from_slice(chunk);
+			let read = chunk.len();
+			self.inner.consume(read);
+
+			let mut units: Vec<u16> = self
+				.pending
+				.chunks_exact(2)
+				.map(|pair| match self.encoding {
+					Encoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+					_ => u16::from_be_bytes([pair[0], pair[1]]),
+				})
+				.collect();
+			// a high surrogate waits for the low one in the next chunk
+			let mut kept = self.pending.len() % 2;
+			if matches!(units.last(), Some(0xD800..=0xDBFF)) {
+				units.pop();
+				kept += 2;
+			}
+
+			for c in char::decode_utf16(units) {
+				push_char(&mut self.buffer, c.unwrap_or(char::REPLACEMENT_CHARACTER));
+			}
+			self.pending.drain(..self.pending.len() - kept);
+		}
+
+		Ok(())
+	}
+}
+
+fn push_char(buffer: &mut Vec<u8>, c: char) {
+	let mut bytes = [0; 4];
+	buffer.extend_from_slice(c.encode_utf8(&mut bytes).as_bytes());
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+	fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+		let available = self.fill_buf()?;
+		let read = available.len().min(out.len());
+		out[..read].copy_from_slice(&available[..read]);
+		self.consume(read);
+		Ok(read)
+	}
+}
+
+impl<R: BufRead> BufRead for Decoder<R> {
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		if self.encoding == Encoding::Utf8 {
+			return self.inner.fill_buf();
+		}
+		if self.pos >= self.buffer.len() {
+			self.transcode()?;
+		}
+		Ok(&self.buffer[self.pos..])
+	}
+
+	fn consume(&mut self, amount: usize) {
+		match self.encoding {
+			Encoding::Utf8 => self.inner.consume(amount),
+			_ => self.pos += amount,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn decode(bytes: &[u8]) -> String {
+		let mut text = String::new();
+		// a tiny buffer splits characters between reads
+		let reader = io::BufReader::with_capacity(3, bytes);
+		Decoder::new(reader)
+			.unwrap()
+			.read_to_string(&mut text)
+			.unwrap();
+		text
+	}
+
+	#[test]
+	fn byte_order_marks() {
+		assert_eq!("plain", decode(b"plain"));
+		assert_eq!("héllo", decode("\u{feff}héllo".as_bytes()));
+
+		let text = "héllo 😀\n";
+		let le: Vec<u8> = [0xFF, 0xFE]
+			.into_iter()
+			.chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+			.collect();
+		let be: Vec<u8> = [0xFE, 0xFF]
+			.into_iter()
+			.chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+			.collect();
+		assert_eq!(text, decode(&le));
+		assert_eq!(text, decode(&be));
+		assert_eq!("a\u{fffd}", decode(&[0xFF, 0xFE, b'a', 0, b'b']));
+	}
+}
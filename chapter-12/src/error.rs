@@ -0,0 +1,178 @@
+// everything that can stop minigrep, so callers can tell a bad argument
+// from a bad pattern or a file that can't be read
+//
+// the message of each error only describes its own part, and what caused it
+// is its source, so the whole story is the message followed by its sources:
+// "can't read missing.txt for --file: No such file or directory (os error 2)"
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::regex;
+
+#[derive(Debug)]
+pub enum SearchError {
+	// neither a PATTERN nor -e or -f was given
+	MissingPattern,
+	// an option that minigrep doesn't know
+	UnknownOption(String),
+	// an option that needs a value, at the end of the arguments
+	MissingValue {
+		option: String,
+		value: String,
+	},
+	// an option that doesn't take a value, given one with --option=value
+	UnexpectedValue(String),
+	InvalidValue {
+		option: String,
+		value: String,
+		expected: String,
+	},
+	// an argument that can't be used here, like one flag combined with another
+	InvalidArgument {
+		argument: String,
+		reason: String,
+	},
+	// a file named by an argument couldn't be read, like the patterns of -f
+	ArgumentFile {
+		argument: String,
+		path: PathBuf,
+		source: io::Error,
+	},
+	// the config file couldn't be read, or has a bad flag in it
+	ConfigFile {
+		path: PathBuf,
+		source: Box<dyn Error + Send + Sync>,
+	},
+	// a regular expression that doesn't compile
+	Pattern {
+		pattern: String,
+		source: regex::Error,
+	},
+	// a file or directory that couldn't be searched, which doesn't stop the search
+	// of the others
+	Input {
+		path: PathBuf,
+		source: io::Error,
+	},
+	// some inputs couldn't be searched, each of them was reported when it failed
+	Inputs {
+		failed: usize,
+	},
+	// the index of a directory couldn't be built
+	Index {
+		dir: PathBuf,
+		source: io::Error,
+	},
+	// the results couldn't be written
+	Output(io::Error),
+}
+
+impl SearchError {
+	pub fn invalid(argument: &str, reason: &str) -> SearchError {
+		SearchError::InvalidArgument {
+			argument: argument.to_string(),
+			reason: reason.to_string(),
+		}
+	}
+}
+
+impl fmt::Display for SearchError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SearchError::MissingPattern => {
+				write!(f, "missing PATTERN to search for (try '--help' for usage)")
+			}
+			SearchError::UnknownOption(option) => write!(f, "unknown option '{}'", option),
+			SearchError::MissingValue { option, value } => {
+				write!(f, "option '{}' needs a value ({})", option, value)
+			}
+			SearchError::UnexpectedValue(option) => {
+				write!(f, "option '{}' doesn't take a value", option)
+			}
+			SearchError::InvalidValue {
+				option,
+				value,
+				expected,
+			} => write!(
+				f,
+				"invalid value '{}' for '{}': expected {}",
+				value, option, expected
+			),
+			SearchError::InvalidArgument { argument, reason } => {
+				write!(f, "{} {}", argument, reason)
+			}
+			SearchError::ArgumentFile { argument, path, .. } => {
+				write!(f, "can't read {} for {}", path.display(), argument)
+			}
+			SearchError::ConfigFile { path, .. } => {
+				write!(f, "in config file {}", path.display())
+			}
+			SearchError::Pattern { pattern, .. } => write!(f, "pattern '{}'", pattern),
+			SearchError::Input { path, .. } => write!(f, "{}", path.display()),
+			SearchError::Inputs { failed } => {
+				write!(f, "{} input(s) could not be searched", failed)
+			}
+			SearchError::Index { dir, .. } => write!(f, "can't index {}", dir.display()),
+			SearchError::Output(_) => write!(f, "can't write the results"),
+		}
+	}
+}
+
+impl Error for SearchError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			SearchError::ArgumentFile { source, .. }
+			| SearchError::Input { source, .. }
+			| SearchError::Index { source, .. }
+			| SearchError::Output(source) => Some(source),
+			SearchError::ConfigFile { source, .. } => Some(source.as_ref()),
+			SearchError::Pattern { source, .. } => Some(source),
+			_ => None,
+		}
+	}
+}
+
+// the message of `error` followed by the messages of its sources
+pub fn describe(error: &dyn Error) -> String {
+	let mut message = error.to_string();
+	let mut source = error.source();
+	while let Some(cause) = source {
+		message.push_str(": ");
+		message.push_str(&cause.to_string());
+		source = cause.source();
+	}
+	message
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn describes_the_whole_chain() {
+		let inner = SearchError::UnknownOption(String::from("--colour"));
+		let error = SearchError::ConfigFile {
+			path: PathBuf::from("minigreprc"),
+			source: Box::new(inner),
+		};
+		assert_eq!(
+			"in config file minigreprc: unknown option '--colour'",
+			describe(&error)
+		);
+
+		let error = SearchError::Pattern {
+			pattern: String::from("a("),
+			source: regex::Regex::new("a(").unwrap_err(),
+		};
+		assert!(describe(&error).starts_with("pattern 'a(': invalid regex at position"));
+
+		let error = SearchError::Input {
+			path: PathBuf::from("logs/app.log"),
+			source: io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied"),
+		};
+		assert_eq!("logs/app.log: Permission denied", describe(&error));
+	}
+}
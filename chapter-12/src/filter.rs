@@ -0,0 +1,171 @@
+// choosing the files a directory search reads by their name, with globs (-g)
+// and file types (-t)
+//
+// like ripgrep, a glob selects the files it matches and a glob starting with
+// '!' leaves them out; the last glob that matches a file decides, and when there
+// are selecting globs, a file that none of them matches is left out as well
+// a glob without a '/' matches the file name at any depth, any other glob the
+// path relative to the searched directory
+//
+// a type is a named set of globs, and with -t only the files of one of the
+// given types are read
+
+use crate::error::SearchError;
+use crate::glob::Glob;
+
+// the types -t understands, with the globs of their file names
+pub const TYPES: &[(&str, &[&str])] = &[
+	("c", &["*.c", "*.h"]),
+	(
+		"cpp",
+		&["*.cpp", "*.cc", "*.cxx", "*.hpp", "*.hh", "*.hxx", "*.h"],
+	),
+	("css", &["*.css", "*.scss"]),
+	("go", &["*.go"]),
+	("html", &["*.html", "*.htm"]),
+	("java", &["*.java"]),
+	("js", &["*.js", "*.mjs", "*.cjs", "*.jsx"]),
+	("json", &["*.json"]),
+	("markdown", &["*.md", "*.markdown"]),
+	("py", &["*.py", "*.pyi"]),
+	("rust", &["*.rs"]),
+	("sh", &["*.sh", "*.bash", "*.zsh"]),
+	("sql", &["*.sql"]),
+	("toml", &["*.toml", "Cargo.lock"]),
+	("ts", &["*.ts", "*.tsx"]),
+	("txt", &["*.txt"]),
+	("yaml", &["*.yaml", "*.yml"]),
+];
+
+#[derive(Debug, Clone)]
+struct Rule {
+	glob: Glob,
+	// a glob that starts with '!'
+	exclude: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+	rules: Vec<Rule>,
+	// the globs of every type given with -t
+	types: Vec<Glob>,
+}
+
+impl Filter {
+	pub fn add_glob(&mut self, pattern: &str) -> Result<(), SearchError> {
+		let (exclude, glob) = match pattern.strip_prefix('!') {
+			Some(glob) => (true, glob),
+			None => (false, pattern),
+		};
+		self.rules.push(Rule {
+			glob: compile(glob)?,
+			exclude,
+		});
+		Ok(())
+	}
+
+	pub fn add_type(&mut self, name: &str) -> Result<(), SearchError> {
+		let (_, globs) = TYPES
+			.iter()
+			.find(|(type_name, _)| *type_name == name)
+			.ok_or_else(|| SearchError::InvalidValue {
+				option: String::from("--type"),
+				value: name.to_string(),
+				expected: String::from("one of the types --type-list shows"),
+			})?;
+		for glob in globs.iter() {
+			self.types.push(compile(glob)?);
+		}
+		Ok(())
+	}
+
+	// whether the walk reads the file at `path`, relative to the searched directory
+	// with '/' separators
+	pub fn allows_file(&self, path: &str) -> bool {
+		let selected = match self
+			.rules
+			.iter()
+			.rev()
+			.find(|rule| rule.glob.is_match(path))
+		{
+			Some(rule) => !rule.exclude,
+			None => self.rules.iter().all(|rule| rule.exclude),
+		};
+		selected && (self.types.is_empty() || self.types.iter().any(|glob| glob.is_match(path)))
+	}
+
+	// whether the walk goes into the directory at `path`
+	// only a '!' glob keeps it out, a selecting glob is about the files inside
+	pub fn allows_dir(&self, path: &str) -> bool {
+		self.rules
+			.iter()
+			.rev()
+			.find(|rule| rule.glob.is_match(path))
+			.is_none_or(|rule| !rule.exclude)
+	}
+}
+
+fn compile(glob: &str) -> Result<Glob, SearchError> {
+	// a name without a directory matches at any depth
+	let anchored = match glob.contains('/') {
+		true => glob.trim_start_matches('/').to_string(),
+		false => format!("**/{}", glob),
+	};
+	Glob::new(&anchored).map_err(|source| SearchError::Pattern {
+		pattern: glob.to_string(),
+		source,
+	})
+}
+
+// what --type-list prints, a line for every type
+pub fn type_list() -> String {
+	TYPES
+		.iter()
+		.map(|(name, globs)| format!("{}: {}\n", name, globs.join(", ")))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn filter(globs: &[&str], types: &[&str]) -> Filter {
+		let mut filter = Filter::default();
+		for glob in globs {
+			filter.add_glob(glob).unwrap();
+		}
+		for name in types {
+			filter.add_type(name).unwrap();
+		}
+		filter
+	}
+
+	#[test]
+	fn globs_and_types() {
+		let everything = filter(&[], &[]);
+		assert!(everything.allows_file("src/Cargo.lock"));
+
+		let no_locks = filter(&["!*.lock"], &[]);
+		assert!(no_locks.allows_file("src/main.rs"));
+		assert!(!no_locks.allows_file("src/Cargo.lock"));
+
+		// the last glob that matches wins
+		let rust = filter(&["*.rs", "!src/gen/**", "src/gen/keep.rs"], &[]);
+		assert!(rust.allows_file("main.rs"));
+		assert!(rust.allows_file("src/lib/a.rs"));
+		assert!(!rust.allows_file("README.md"));
+		assert!(!rust.allows_file("src/gen/out.rs"));
+		assert!(rust.allows_file("src/gen/keep.rs"));
+		// the inside of a directory isn't the directory itself
+		assert!(rust.allows_dir("src/gen"));
+		assert!(!filter(&["!target"], &[]).allows_dir("sub/target"));
+
+		let toml = filter(&["!target/**"], &["toml"]);
+		assert!(toml.allows_file("Cargo.toml"));
+		assert!(toml.allows_file("sub/Cargo.lock"));
+		assert!(!toml.allows_file("src/main.rs"));
+		assert!(!toml.allows_file("target/x.toml"));
+
+		assert!(Filter::default().add_type("cobol").is_err());
+	}
+}
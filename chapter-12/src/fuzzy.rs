@@ -0,0 +1,126 @@
+// approximate matching: finding substrings within a few typos of the query
+//
+// the distance between two strings is their levenshtein distance, the number of
+// characters that have to be inserted, deleted or substituted to turn one into the other
+//
+// substrings are found with sellers' algorithm, the dynamic programming table of the
+// levenshtein distance where a match may start anywhere in the text for free
+// it is computed one column per character of the text, so only a single column of
+// query length is kept; every cell also remembers where its alignment started
+
+use std::cmp;
+
+use crate::casefold;
+
+#[derive(Debug, Clone)]
+pub struct Fuzzy {
+	query: Vec<char>,
+	max_distance: usize,
+	ignore_case: bool,
+}
+
+impl Fuzzy {
+	pub fn new(query: &str, max_distance: usize, ignore_case: bool) -> Fuzzy {
+		Fuzzy {
+			query: query.chars().collect(),
+			max_distance,
+			ignore_case,
+		}
+	}
+
+	// the byte range and distance of the first match in `text` at or after `start`
+	// once a substring is close enough, a closer one that overlaps it is preferred,
+	// which includes growing the match as long as that brings it closer
+	pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize, usize)> {
+		let len = self.query.len();
+		// the cost and start of the cheapest alignment of the first i query characters
+		// that ends at the current position of the text
+		let mut column: Vec<(usize, usize)> = (0..=len).map(|i| (i, start)).collect();
+		let mut best = (len <= self.max_distance).then_some((len, start, start));
+
+		for (i, c) in text[start..].char_indices() {
+			let end = start + i + c.len_utf8();
+			let mut diagonal = column[0];
+			column[0] = (0, end);
+			for j in 1..=len {
+				let substituted = (
+					diagonal.0 + usize::from(!self.same(self.query[j - 1], c)),
+					diagonal.1,
+				);
+				let inserted = (column[j].0 + 1, column[j].1);
+				let deleted = (column[j - 1].0 + 1, column[j - 1].1);
+				diagonal = column[j];
+				// on equal costs the earlier start, and so the longer match, wins
+				column[j] = cmp::min(substituted, cmp::min(inserted, deleted));
+			}
+
+			let (cost, from) = column[len];
+			match best {
+				Some((distance, _, _)) if cost < distance => best = Some((cost, from, end)),
+				// a closer match can only come from an alignment that started within this one
+				Some((distance, _, best_end)) => {
+					let closer =
+						|&(cost, from): &(usize, usize)| cost < distance && from < best_end;
+					if !column.iter().any(closer) {
+						break;
+					}
+				}
+				None if cost <= self.max_distance => best = Some((cost, from, end)),
+				None => {}
+			}
+		}
+
+		best.map(|(distance, s, e)| (s, e, distance))
+	}
+
+	// the levenshtein distance between the query and all of `text`
+	pub fn distance(&self, text: &str) -> usize {
+		let mut column: Vec<usize> = (0..=self.query.len()).collect();
+		for (i, c) in text.chars().enumerate() {
+			let mut diagonal = column[0];
+			column[0] = i + 1;
+			for j in 1..=self.query.len() {
+				let substituted = diagonal + usize::from(!self.same(self.query[j - 1], c));
+				diagonal = column[j];
+				column[j] = cmp::min(substituted, cmp::min(column[j], column[j - 1]) + 1);
+			}
+		}
+		column[self.query.len()]
+	}
+
+	fn same(&self, a: char, b: char) -> bool {
+		a == b || (self.ignore_case && casefold::fold(a).eq(casefold::fold(b)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_substrings_within_the_distance() {
+		let fuzzy = Fuzzy::new("connection", 2, false);
+		assert_eq!(Some((6, 15, 1)), fuzzy.find_at("lost: conection reset", 0));
+		assert_eq!(Some((0, 10, 0)), fuzzy.find_at("connection", 0));
+		assert_eq!(Some((0, 11, 1)), fuzzy.find_at("connnection", 0));
+		assert_eq!(None, fuzzy.find_at("selection", 0));
+		assert_eq!(1, fuzzy.distance("conection"));
+		assert_eq!(2, fuzzy.distance("collection"));
+
+		let fuzzy = Fuzzy::new("abc", 1, false);
+		assert_eq!(Some((0, 3, 0)), fuzzy.find_at("abcd", 0));
+		// matches that don't overlap are found one after the other
+		assert_eq!(Some((0, 2, 1)), fuzzy.find_at("bc abc", 0));
+		assert_eq!(Some((3, 6, 0)), fuzzy.find_at("bc abc", 2));
+		// but of overlapping ones the closest wins
+		let fuzzy = Fuzzy::new("aab", 1, false);
+		assert_eq!(Some((1, 4, 0)), fuzzy.find_at("aaab", 0));
+	}
+
+	#[test]
+	fn ignore_case() {
+		let fuzzy = Fuzzy::new("Timeout", 1, true);
+		assert_eq!(Some((0, 6, 1)), fuzzy.find_at("TIMOUT", 0));
+		assert_eq!(None, Fuzzy::new("Timeout", 1, false).find_at("TIMOUT", 0));
+	}
+}
@@ -0,0 +1,178 @@
+// shell style glob patterns for paths, as used by ignore files
+//
+// - `*` matches anything except a '/'
+// - `**` matches anything, including '/', when it is a whole path component:
+//   `**/a` matches a in any directory, `a/**` everything inside a,
+//   and `a/**/b` zero or more directories between a and b
+// - `?` matches a single character except '/'
+// - `[abc]`, `[a-z]` and the negated `[!a-z]` (or `[^a-z]`) match one character of a set
+// - `\` makes the next character literal
+//
+// a glob is translated into a regular expression and matched with our regex engine
+
+use std::fmt;
+
+use crate::regex::{self, Regex};
+
+#[derive(Debug, Clone)]
+pub struct Glob {
+	pattern: String,
+	regex: Regex,
+}
+
+impl Glob {
+	pub fn new(pattern: &str) -> Result<Glob, regex::Error> {
+		let options = regex::Options {
+			whole_line: true,
+			..regex::Options::default()
+		};
+		let regex = Regex::with_options(&to_regex(pattern), options)?;
+		Ok(Glob {
+			pattern: pattern.to_string(),
+			regex,
+		})
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.pattern
+	}
+
+	// `path` uses '/' to separate its components
+	pub fn is_match(&self, path: &str) -> bool {
+		self.regex.is_match(path)
+	}
+}
+
+impl fmt::Display for Glob {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.pattern)
+	}
+}
+
+fn to_regex(glob: &str) -> String {
+	let chars: Vec<char> = glob.chars().collect();
+	let mut regex = String::new();
+	let mut i = 0;
+
+	while i < chars.len() {
+		match chars[i] {
+			'*' if chars.get(i + 1) == Some(&'*') => {
+				let starts_component = i == 0 || chars[i - 1] == '/';
+				let after = chars.get(i + 2);
+				if starts_component && after == Some(&'/') {
+					// "**/": any number of leading directories
+					regex.push_str("(?:.*/)?");
+					i += 3;
+				} else if starts_component && after.is_none() {
+					// a trailing "**": everything
+					regex.push_str(".*");
+					i += 2;
+				} else {
+					// "**" inside a component is just a "*"
+					regex.push_str("[^/]*");
+					i += 2;
+				}
+			}
+			'*' => {
+				regex.push_str("[^/]*");
+				i += 1;
+			}
+			'?' => {
+				regex.push_str("[^/]");
+				i += 1;
+			}
+			'[' => match class_end(&chars, i) {
+				Some(end) => {
+					regex.push('[');
+					let mut j = i + 1;
+					if chars[j] == '!' || chars[j] == '^' {
+						regex.push('^');
+						j += 1;
+					}
+					for &c in &chars[j..end] {
+						// only a backslash needs protecting inside a regex class
+						if c == '\\' {
+							regex.push('\\');
+						}
+						regex.push(c);
+					}
+					regex.push(']');
+					i = end + 1;
+				}
+				// an unclosed '[' is a literal
+				None => {
+					regex.push_str("\\[");
+					i += 1;
+				}
+			},
+			'\\' if i + 1 < chars.len() => {
+				push_literal(&mut regex, chars[i + 1]);
+				i += 2;
+			}
+			c => {
+				push_literal(&mut regex, c);
+				i += 1;
+			}
+		}
+	}
+
+	regex
+}
+
+// the index of the ']' closing the class that starts at `start`
+fn class_end(chars: &[char], start: usize) -> Option<usize> {
+	let mut i = start + 1;
+	if matches!(chars.get(i), Some('!') | Some('^')) {
+		i += 1;
+	}
+	// a ']' right at the start of the class is a literal
+	if chars.get(i) == Some(&']') {
+		i += 1;
+	}
+	(i..chars.len()).find(|&j| chars[j] == ']')
+}
+
+fn push_literal(regex: &mut String, c: char) {
+	if !c.is_alphanumeric() && c != '/' && c != '_' && !c.is_whitespace() {
+		regex.push('\\');
+	}
+	regex.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn matches(glob: &str, path: &str) -> bool {
+		Glob::new(glob).unwrap().is_match(path)
+	}
+
+	#[test]
+	fn stars_and_questions() {
+		assert!(matches("*.rs", "main.rs"));
+		assert!(!matches("*.rs", "src/main.rs"));
+		assert!(matches("src/*.rs", "src/main.rs"));
+		assert!(matches("file?.txt", "file1.txt"));
+		assert!(!matches("file?.txt", "file10.txt"));
+		assert!(matches("a.(b)+", "a.(b)+"));
+	}
+
+	#[test]
+	fn double_stars() {
+		assert!(matches("**/*.rs", "main.rs"));
+		assert!(matches("**/*.rs", "src/deep/main.rs"));
+		assert!(matches("target/**", "target/debug/build"));
+		assert!(matches("a/**/b", "a/b"));
+		assert!(matches("a/**/b", "a/x/y/b"));
+		assert!(!matches("a/**/b", "a/x/c"));
+	}
+
+	#[test]
+	fn classes() {
+		assert!(matches("[abc].txt", "b.txt"));
+		assert!(matches("[a-c].txt", "c.txt"));
+		assert!(!matches("[!a-c].txt", "c.txt"));
+		assert!(matches("[!a-c].txt", "d.txt"));
+		assert!(matches("[]].txt", "].txt"));
+	}
+}
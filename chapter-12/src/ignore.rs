@@ -0,0 +1,127 @@
+// rules from .gitignore style files, which keep the directory walk out of
+// build output and other files nobody wants to search
+//
+// the syntax follows gitignore:
+// - blank lines and lines starting with '#' are skipped
+// - a leading '!' re-includes what an earlier rule ignored
+// - a trailing '/' only matches directories
+// - a pattern with a '/' at the start or in the middle is relative to the directory
+//   of the ignore file, any other pattern matches a name at any depth below it
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::glob::Glob;
+
+// the ignore files read in every directory, from the lowest to the highest precedence
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore", ".minigrepignore"];
+
+#[derive(Debug, Clone)]
+struct Rule {
+	glob: Glob,
+	negated: bool,
+	dir_only: bool,
+}
+
+// the rules of the ignore files of a single directory
+#[derive(Debug, Clone)]
+pub struct Ignore {
+	dir: PathBuf,
+	rules: Vec<Rule>,
+}
+
+impl Ignore {
+	pub fn parse(dir: &Path, contents: &str) -> Ignore {
+		let rules = contents.lines().filter_map(parse_rule).collect();
+		Ignore {
+			dir: dir.to_path_buf(),
+			rules,
+		}
+	}
+
+	// reads every ignore file of `dir`, None if there isn't any
+	pub fn read_dir(dir: &Path) -> Option<Ignore> {
+		let mut contents = String::new();
+		for name in IGNORE_FILES {
+			// a missing or unreadable ignore file is the same as an empty one
+			if let Ok(file) = fs::read_to_string(dir.join(name)) {
+				contents.push_str(&file);
+				contents.push('\n');
+			}
+		}
+		let ignore = Ignore::parse(dir, &contents);
+		(!ignore.rules.is_empty()).then_some(ignore)
+	}
+
+	// Some(true) if `path` is ignored, Some(false) if a '!' rule re-includes it,
+	// and None if no rule mentions it
+	pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+		let relative = path.strip_prefix(&self.dir).ok()?;
+		let relative: Vec<_> = relative
+			.components()
+			.map(|c| c.as_os_str().to_string_lossy())
+			.collect();
+		let relative = relative.join("/");
+
+		// the last rule that matches wins
+		self.rules
+			.iter()
+			.rev()
+			.find(|rule| (is_dir || !rule.dir_only) && rule.glob.is_match(&relative))
+			.map(|rule| !rule.negated)
+	}
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+	let line = line.trim_end();
+	if line.is_empty() || line.starts_with('#') {
+		return None;
+	}
+
+	let (negated, line) = match line.strip_prefix('!') {
+		Some(rest) => (true, rest),
+		None => (false, line.strip_prefix('\\').unwrap_or(line)),
+	};
+	let (dir_only, line) = match line.strip_suffix('/') {
+		Some(rest) => (true, rest),
+		None => (false, line),
+	};
+
+	let pattern = match line.strip_prefix('/') {
+		Some(anchored) => anchored.to_string(),
+		None if line.contains('/') => line.to_string(),
+		None => format!("**/{}", line),
+	};
+	// a pattern that doesn't compile is skipped, like git does
+	let glob = Glob::new(&pattern).ok()?;
+
+	Some(Rule {
+		glob,
+		negated,
+		dir_only,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn gitignore_rules() {
+		let ignore = Ignore::parse(
+			Path::new("/repo"),
+			"# build output\n/target\n*.log\n!keep.log\nbuild/\ndocs/*.html\n",
+		);
+		let ignored = |path: &str, is_dir| ignore.matched(Path::new(path), is_dir);
+
+		assert_eq!(Some(true), ignored("/repo/target", true));
+		assert_eq!(None, ignored("/repo/src/target", true));
+		assert_eq!(Some(true), ignored("/repo/src/debug.log", false));
+		assert_eq!(Some(false), ignored("/repo/keep.log", false));
+		assert_eq!(Some(true), ignored("/repo/a/build", true));
+		assert_eq!(None, ignored("/repo/a/build", false));
+		assert_eq!(Some(true), ignored("/repo/docs/index.html", false));
+		assert_eq!(None, ignored("/repo/docs/api/index.html", false));
+		assert_eq!(None, ignored("/repo/src/main.rs", false));
+	}
+}
@@ -0,0 +1,366 @@
+// a trigram index of a directory tree, so repeated searches only read the files
+// that can possibly match
+//
+// `minigrep index DIR` records, for every file the walk finds, the set of trigrams
+// (three byte sequences) of its case folded text, in DIR/.minigrep-index
+// a literal pattern can only match a file that has every trigram of the folded pattern,
+// and folding keeps that true for case sensitive and case insensitive search alike
+//
+// running `minigrep index DIR` again only rereads the files whose size or modification
+// time changed; and a search with --index still reads every file that changed since
+// the index was built, so an outdated index makes searches slower but never wrong
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::UNIX_EPOCH;
+
+use crate::casefold;
+use crate::decode::Decoder;
+use crate::walk::{self, relative_name, Walk, WalkOptions};
+
+pub const INDEX_FILE: &str = ".minigrep-index";
+
+const MAGIC: &[u8] = b"minigrep-index 1\n";
+
+// what a file looked like when it was indexed: its size and modification time
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Stamp {
+	len: u64,
+	seconds: u64,
+	nanos: u32,
+}
+
+impl Stamp {
+	fn of(path: &Path) -> io::Result<Stamp> {
+		let metadata = fs::metadata(path)?;
+		// without a modification time every update rereads the file
+		let modified = metadata
+			.modified()
+			.ok()
+			.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+			.unwrap_or_default();
+		Ok(Stamp {
+			len: metadata.len(),
+			seconds: modified.as_secs(),
+			nanos: modified.subsec_nanos(),
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+	// relative to the indexed directory, with '/' separators
+	path: String,
+	stamp: Stamp,
+	// sorted and without duplicates
+	trigrams: Vec<u32>,
+}
+
+#[derive(Debug, Default)]
+pub struct Index {
+	files: Vec<Entry>,
+	// the files that have each trigram, by their position in `files`
+	postings: HashMap<u32, Vec<usize>>,
+}
+
+// what updating an index did
+#[derive(Debug, Default)]
+pub struct Summary {
+	pub files: usize,
+	pub updated: usize,
+	pub removed: usize,
+	// the entries that couldn't be read, which are left out of the index, so
+	// searches with --index read them like any file the index doesn't know
+	pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+impl Index {
+	// the index of `root`, which must have been built already
+	pub fn load(root: &Path) -> io::Result<Index> {
+		let file = File::open(root.join(INDEX_FILE))?;
+		let size = file.metadata()?.len();
+		let mut reader = BufReader::new(file);
+		let mut magic = vec![0; MAGIC.len()];
+		reader.read_exact(&mut magic)?;
+		if magic != MAGIC {
+			return Err(invalid("not a minigrep index"));
+		}
+
+		// a path, a stamp and a trigram count
+		let count = read_len(&mut reader, size, 28)?;
+		let mut files = Vec::new();
+		for _ in 0..count {
+			let len = read_len(&mut reader, size, 1)?;
+			let mut path = vec![0; len];
+			reader.read_exact(&mut path)?;
+			let path = String::from_utf8(path).map_err(|_| invalid("corrupt index"))?;
+			let stamp = Stamp {
+				len: read_u64(&mut reader)?,
+				seconds: read_u64(&mut reader)?,
+				nanos: read_u32(&mut reader)?,
+			};
+			let count = read_len(&mut reader, size, 4)?;
+			let trigrams = (0..count)
+				.map(|_| read_u32(&mut reader))
+				.collect::<io::Result<_>>()?;
+			files.push(Entry {
+				path,
+				stamp,
+				trigrams,
+			});
+		}
+
+		Ok(Index::from_entries(files))
+	}
+
+	// builds the index of `root`, or brings an existing one up to date
+	pub fn update(root: &Path, options: &WalkOptions) -> io::Result<(Index, Summary)> {
+		// an index that can't be read is simply built again
+		let old = Index::load(root).unwrap_or_default();
+		let mut old: HashMap<String, Entry> = old
+			.files
+			.into_iter()
+			.map(|entry| (entry.path.clone(), entry))
+			.collect();
+
+		let walked = walk::walk(root, options);
+		let mut summary = Summary {
+			errors: walked.errors,
+			..Summary::default()
+		};
+		let mut files = Vec::new();
+		for path in walked.files {
+			let relative = relative_name(root, &path);
+			if relative == INDEX_FILE {
+				continue;
+			}
+			let stamp = match Stamp::of(&path) {
+				Ok(stamp) => stamp,
+				Err(e) => {
+					summary.errors.push((path, e));
+					continue;
+				}
+			};
+			match old.remove(&relative) {
+				Some(entry) if entry.stamp == stamp => files.push(entry),
+				_ => match read_text(&path) {
+					Ok(text) => {
+						summary.updated += 1;
+						files.push(Entry {
+							path: relative,
+							stamp,
+							trigrams: trigrams(&text),
+						});
+					}
+					Err(e) => summary.errors.push((path, e)),
+				},
+			}
+		}
+		summary.files = files.len();
+		summary.removed = old.len();
+
+		let index = Index::from_entries(files);
+		index.save(root)?;
+		Ok((index, summary))
+	}
+
+	// writes the index to a temporary file first, so a search never reads half of one
+	fn save(&self, root: &Path) -> io::Result<()> {
+		let temporary = root.join(format!("{}.{}", INDEX_FILE, process::id()));
+		let result = (|| {
+			let mut writer = BufWriter::new(File::create(&temporary)?);
+			writer.write_all(MAGIC)?;
+			writer.write_all(&(self.files.len() as u32).to_le_bytes())?;
+			for entry in &self.files {
+				writer.write_all(&(entry.path.len() as u32).to_le_bytes())?;
+				writer.write_all(entry.path.as_bytes())?;
+				writer.write_all(&entry.stamp.len.to_le_bytes())?;
+				writer.write_all(&entry.stamp.seconds.to_le_bytes())?;
+				writer.write_all(&entry.stamp.nanos.to_le_bytes())?;
+				writer.write_all(&(entry.trigrams.len() as u32).to_le_bytes())?;
+				for trigram in &entry.trigrams {
+					writer.write_all(&trigram.to_le_bytes())?;
+				}
+			}
+			writer
+				.into_inner()
+				.map_err(|e| e.into_error())?
+				.sync_all()?;
+			fs::rename(&temporary, root.join(INDEX_FILE))
+		})();
+		if result.is_err() {
+			let _ = fs::remove_file(&temporary);
+		}
+		result
+	}
+
+	fn from_entries(files: Vec<Entry>) -> Index {
+		let mut postings: HashMap<u32, Vec<usize>> = HashMap::new();
+		for (i, entry) in files.iter().enumerate() {
+			for &trigram in &entry.trigrams {
+				postings.entry(trigram).or_default().push(i);
+			}
+		}
+		Index { files, postings }
+	}
+
+	// the files below `root` that a search for any of the literal `patterns` has to read:
+	// the indexed files that have every trigram of one of the patterns,
+	// and every file that changed or appeared since the index was built
+	pub fn candidates(&self, root: &Path, options: &WalkOptions, patterns: &[String]) -> Walk {
+		let mut possible: HashSet<usize> = HashSet::new();
+		for pattern in patterns {
+			let required = trigrams(&casefold::fold_str(pattern));
+			// too short to narrow anything down
+			if required.is_empty() {
+				possible.extend(0..self.files.len());
+				break;
+			}
+			let mut files: Option<HashSet<usize>> = None;
+			for trigram in required {
+				let with: HashSet<usize> = match self.postings.get(&trigram) {
+					Some(with) => with.iter().copied().collect(),
+					None => HashSet::new(),
+				};
+				files = Some(match files {
+					Some(files) => files.intersection(&with).copied().collect(),
+					None => with,
+				});
+			}
+			possible.extend(files.unwrap_or_default());
+		}
+
+		let indexed: HashMap<&str, usize> = self
+			.files
+			.iter()
+			.enumerate()
+			.map(|(i, entry)| (entry.path.as_str(), i))
+			.collect();
+		let mut walked = walk::walk(root, options);
+		let mut candidates = Vec::new();
+		for path in walked.files {
+			let relative = relative_name(root, &path);
+			if relative == INDEX_FILE {
+				continue;
+			}
+			let keep = match indexed.get(relative.as_str()) {
+				Some(&i) => {
+					possible.contains(&i) || Stamp::of(&path).ok() != Some(self.files[i].stamp)
+				}
+				None => true,
+			};
+			if keep {
+				candidates.push(path);
+			}
+		}
+		walked.files = candidates;
+		walked
+	}
+}
+
+// the distinct trigrams of `text`, sorted, each packed into the low 24 bits of a u32
+pub fn trigrams(text: &str) -> Vec<u32> {
+	let mut trigrams: Vec<u32> = text
+		.as_bytes()
+		.windows(3)
+		.map(|w| (w[0] as u32) << 16 | (w[1] as u32) << 8 | w[2] as u32)
+		.collect();
+	trigrams.sort_unstable();
+	trigrams.dedup();
+	trigrams
+}
+
+// the folded text of a file, decoded like a search decodes it
+fn read_text(path: &Path) -> io::Result<String> {
+	let mut bytes = Vec::new();
+	Decoder::new(BufReader::new(File::open(path)?))?.read_to_end(&mut bytes)?;
+	Ok(casefold::fold_str(&String::from_utf8_lossy(&bytes)))
+}
+
+fn invalid(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+	let mut bytes = [0; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+// the number of items of `unit` bytes that follow, which can't be more than the
+// rest of the file holds, so a corrupt index fails instead of allocating for them
+fn read_len(reader: &mut BufReader<File>, size: u64, unit: u64) -> io::Result<usize> {
+	let len = u64::from(read_u32(reader)?);
+	let left = size.saturating_sub(reader.stream_position()?);
+	if len * unit > left {
+		return Err(invalid("corrupt index"));
+	}
+	Ok(len as usize)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+	let mut bytes = [0; 8];
+	reader.read_exact(&mut bytes)?;
+	Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn narrows_and_updates_incrementally() {
+		let root = std::env::temp_dir().join(format!("minigrep-index-{}", process::id()));
+		fs::create_dir_all(root.join("src")).unwrap();
+		fs::write(root.join("src/a.rs"), "fn Connect() {}").unwrap();
+		fs::write(root.join("src/b.rs"), "fn disconnect() {}").unwrap();
+		fs::write(root.join("notes.txt"), "nothing to see").unwrap();
+		let options = WalkOptions::default();
+		let names = |walked: Walk| -> Vec<String> {
+			walked
+				.files
+				.iter()
+				.map(|f| relative_name(&root, f))
+				.collect()
+		};
+
+		let (_, summary) = Index::update(&root, &options).unwrap();
+		let index = Index::load(&root).unwrap();
+		let connect = names(index.candidates(&root, &options, &[String::from("connect")]));
+		let short = names(index.candidates(&root, &options, &[String::from("fn")]));
+
+		fs::write(root.join("notes.txt"), "connect later").unwrap();
+		fs::remove_file(root.join("src/b.rs")).unwrap();
+		let stale = names(index.candidates(&root, &options, &[String::from("connect")]));
+		let (_, second) = Index::update(&root, &options).unwrap();
+		let _ = fs::remove_dir_all(&root);
+
+		let counts = |s: Summary| (s.files, s.updated, s.removed, s.errors.len());
+		assert_eq!((3, 3, 0, 0), counts(summary));
+		assert_eq!(vec!["src/a.rs", "src/b.rs"], connect);
+		assert_eq!(3, short.len());
+		// the changed file is read although the index says it can't match
+		assert_eq!(vec!["notes.txt", "src/a.rs"], stale);
+		assert_eq!((2, 1, 1, 0), counts(second));
+	}
+
+	#[test]
+	fn corrupt_lengths_are_invalid_data() {
+		let root = std::env::temp_dir().join(format!("minigrep-corrupt-{}", process::id()));
+		fs::create_dir_all(&root).unwrap();
+		let write = |rest: &[u8]| fs::write(root.join(INDEX_FILE), [MAGIC, rest].concat()).unwrap();
+
+		// a single file, whose path is said to take 4 GiB
+		write(&[1, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, b'a']);
+		let path = Index::load(&root).unwrap_err();
+		write(&[0xFF, 0xFF, 0xFF, 0xFF]);
+		let files = Index::load(&root).unwrap_err();
+		let _ = fs::remove_dir_all(&root);
+
+		assert_eq!(io::ErrorKind::InvalidData, path.kind());
+		assert_eq!(io::ErrorKind::InvalidData, files.kind());
+	}
+}
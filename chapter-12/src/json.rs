@@ -0,0 +1,40 @@
+// just enough json to write minigrep's --json events
+
+use std::fmt::Write;
+
+// `s` as a quoted json string
+pub fn string(s: &str) -> String {
+	let mut quoted = String::with_capacity(s.len() + 2);
+	quoted.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			// the other control characters have no short escape
+			c if c.is_control() && (c as u32) < 0x20 => {
+				write!(quoted, "\\u{:04x}", c as u32).unwrap();
+			}
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn escapes_strings() {
+		assert_eq!(r#""plain""#, string("plain"));
+		assert_eq!(
+			r#""a \"quote\" \\ and\ttab\n""#,
+			string("a \"quote\" \\ and\ttab\n")
+		);
+		assert_eq!(r#""\u0001""#, string("\u{1}"));
+	}
+}
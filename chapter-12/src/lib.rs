@@ -1,11 +1,79 @@
-use std::env;
-use std::error::Error;
-use std::fs;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+pub mod aho_corasick;
+pub mod args;
+pub mod casefold;
+pub mod decode;
+pub mod error;
+pub mod filter;
+pub mod fuzzy;
+pub mod glob;
+pub mod ignore;
+pub mod index;
+pub mod json;
+pub mod matcher;
+pub mod memmem;
+pub mod multiline;
+pub mod parallel;
+pub mod printer;
+pub mod regex;
+pub mod replace;
+pub mod searcher;
+pub mod walk;
+pub mod watch;
+
+use args::Command;
+use decode::Decoder;
+pub use error::SearchError;
+pub use matcher::{find_matches, Match, Matcher};
+use printer::{BinaryFiles, ColorChoice, OutputMode, PrintOptions, Printer};
+use regex::Regex;
+use replace::Template;
+pub use searcher::{Case, Searcher, SearcherBuilder, Sink, SinkLine, SinkMatch};
 
 pub struct Config {
-	pub query: String,
-	pub filename: String,
-	pub ignore_case: bool,
+	// the patterns to search for, any of which selects a line (-e and -f)
+	pub patterns: Vec<String>,
+	// no files, or a "-", means standard input
+	pub filenames: Vec<String>,
+	// the compiled query, either literal or a regular expression (--regex)
+	pub matcher: Matcher,
+	// print the lines that don't match instead (-v)
+	pub invert: bool,
+	// lines (the default), counts (-c), file names (-l and -L), nothing (-q) or json (--json)
+	pub mode: OutputMode,
+	// stop searching an input after this many selected lines (-m)
+	pub max_count: Option<usize>,
+	// lines of context to print before and after every match (-B, -A and -C)
+	pub before_context: usize,
+	pub after_context: usize,
+	// prefix output lines with their line number (-n) and match column (--column)
+	pub line_number: bool,
+	pub column: bool,
+	// whether to highlight matches (--color=auto|always|never)
+	pub color: ColorChoice,
+	// how inputs with NUL bytes are searched (-a and --binary-files)
+	pub binary_files: BinaryFiles,
+	// let the patterns match across lines, reporting every line a match spans (-U)
+	pub multiline: bool,
+	// rewrite matches with this template instead of printing them (--replace)
+	pub replace: Option<Template>,
+	// write the replacements to the files instead of showing a diff (--in-place),
+	// keeping the originals as FILE.bak (--backup)
+	pub in_place: bool,
+	pub backup: bool,
+	// after searching, keep printing the matching lines appended to the inputs (--watch)
+	pub watch: bool,
+	// search directories through the index that `minigrep index` built (--index)
+	pub index: bool,
+	// which files a directory search skips (--hidden and --no-ignore)
+	pub walk: walk::WalkOptions,
+	// how many files are searched at the same time (-j)
+	pub threads: usize,
 }
 
 // this will make a full copy of the data for the Config instance to own,
@@ -15,66 +83,467 @@ pub struct Config {
 // in this circumstance, giving up a little performance to gain simplicity
 // is a worthwhile trade-off
 impl Config {
-	pub fn new(args: &[String]) -> Result<Config, &'static str> {
-		if args.len() < 3 {
-			return Err("not enough arguments");
+	// see the args module for the flags and where else settings come from
+	pub fn new(args: &[String]) -> Result<Config, SearchError> {
+		let argument = match args::parse(args)? {
+			Command::Search(config) => return Ok(config),
+			Command::Help => "--help",
+			Command::Version => "--version",
+			Command::TypeList => "--type-list",
+			Command::Index { .. } => "index",
+		};
+		Err(SearchError::invalid(argument, "doesn't start a search"))
+	}
+}
+
+// the name grep uses for standard input in its output
+const STDIN_NAME: &str = "(standard input)";
+
+// something to search
+struct Input {
+	// None for standard input
+	path: Option<PathBuf>,
+	// how the input is named in the output and in errors
+	name: String,
+}
+
+// every error is a SearchError, so callers can tell what went wrong
+// an input that can't be searched doesn't stop the search of the others, it is handed
+// to `on_error` as a SearchError::Input when it fails, and the search ends with
+// SearchError::Inputs if any did
+// on success, tells whether anything was found, so main can choose the exit status
+pub fn run(config: Config, on_error: &mut dyn FnMut(SearchError)) -> Result<bool, SearchError> {
+	let stdin = [String::from("-")];
+	let names = if config.filenames.is_empty() {
+		&stdin[..]
+	} else {
+		&config.filenames[..]
+	};
+
+	// hits are prefixed with their file whenever they could come from more than one
+	let with_filename = names.len() > 1 || names.iter().any(|name| Path::new(name).is_dir());
+
+	// an unreadable file doesn't stop the search of the others
+	let mut failures = 0;
+	let mut inputs = Vec::new();
+	for name in names {
+		let path = Path::new(name);
+		if name == "-" {
+			inputs.push(Input {
+				path: None,
+				name: STDIN_NAME.to_string(),
+			});
+		} else if path.is_dir() {
+			match directory_files(&config, path) {
+				Ok(walked) => {
+					inputs.extend(walked.files.into_iter().map(|file| Input {
+						name: file.display().to_string(),
+						path: Some(file),
+					}));
+					for (path, source) in walked.errors {
+						on_error(SearchError::Input { path, source });
+						failures += 1;
+					}
+				}
+				Err(source) => {
+					on_error(SearchError::Input {
+						path: path.to_path_buf(),
+						source,
+					});
+					failures += 1;
+				}
+			}
+		} else {
+			inputs.push(Input {
+				path: Some(path.to_path_buf()),
+				name: name.clone(),
+			});
 		}
-		let query = args[1].clone();
-		let filename = args[2].clone();
+	}
+
+	if let Some(template) = &config.replace {
+		return run_replace(&config, template, &inputs, failures, on_error);
+	}
+
+	let stdout = io::stdout();
+	let options = PrintOptions {
+		mode: config.mode,
+		invert: config.invert,
+		max_count: config.max_count,
+		with_filename,
+		before: config.before_context,
+		after: config.after_context,
+		line_number: config.line_number,
+		column: config.column,
+		color: config.color.should_color(&stdout),
+		binary_files: config.binary_files,
+		multiline: config.multiline,
+	};
+	let mut printer = Printer::new(stdout.lock(), options.clone());
+
+	// -L succeeds when it lists a file, every other mode when a line was selected
+	// an input that can't be read is reported and the search goes on, but output that
+	// can't be written ends it
+	let mut found = false;
+	let mut report = |result: Result<usize, SearchError>| match result {
+		Ok(count) => {
+			found |= match config.mode {
+				OutputMode::FilesWithoutMatch => count == 0,
+				_ => count > 0,
+			};
+			Ok(())
+		}
+		Err(e @ SearchError::Output(_)) => Err(e),
+		Err(e) => {
+			on_error(e);
+			failures += 1;
+			Ok(())
+		}
+	};
+
+	// with -q the first match settles the result, so there is no point in searching in parallel
+	let quiet = config.mode == OutputMode::Quiet;
+	if config.threads <= 1 || inputs.len() <= 1 || quiet {
+		for input in &inputs {
+			let result = search_input(&config, &mut printer, input);
+			let matched = matches!(result, Ok(count) if count > 0);
+			report(result)?;
+			if quiet && matched {
+				break;
+			}
+		}
+	} else {
+		// every worker prints a whole file into a buffer, and the buffers are written
+		// out in the order of the inputs, so the output doesn't depend on the timing
+		// only about as many buffers as there are threads are kept at a time
+		let mut output = Ok(());
+		parallel::ordered_map(
+			&inputs,
+			config.threads,
+			|input| {
+				let mut buffer = Printer::new(Vec::new(), options.clone());
+				let result = search_input(&config, &mut buffer, input);
+				(buffer, result)
+			},
+			|(buffer, result)| {
+				output = printer
+					.append(buffer)
+					.map_err(SearchError::Output)
+					.and_then(|()| report(result));
+				output.is_ok()
+			},
+		);
+		output?;
+	}
+	if config.watch {
+		return watch(&config, names, &mut printer, on_error);
+	}
+	printer.finish().map_err(SearchError::Output)?;
+
+	// like grep, a match found with -q is a success even if some inputs failed
+	if failures > 0 && !(quiet && found) {
+		return Err(SearchError::Inputs { failed: failures });
+	}
+	Ok(found)
+}
 
-		// is_ok returns false if the environment variable isn't set
-		let ignore_case = env::var("IGNORE_CASE").is_ok();
+// how long --watch waits between looking for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// prints the lines that match as they are appended to the inputs, until the process
+// is stopped, so this only returns when the output can't be written anymore
+// context lines don't reach back into what an earlier poll printed
+fn watch<W: Write>(
+	config: &Config,
+	names: &[String],
+	printer: &mut Printer<W>,
+	on_error: &mut dyn FnMut(SearchError),
+) -> Result<bool, SearchError> {
+	let mut watcher = watch::Watcher::new();
+	// the initial search already reported the files it couldn't read
+	for path in watched_files(config, names) {
+		let _ = watcher.track(&path);
+	}
+
+	loop {
+		printer.flush().map_err(SearchError::Output)?;
+		thread::sleep(WATCH_INTERVAL);
+		for path in watched_files(config, names) {
+			match watcher.poll(&path) {
+				Ok(Some(update)) => {
+					let name = path.display().to_string();
+					let reader = match update.reader(&path) {
+						Ok(reader) => reader,
+						Err(source) => {
+							on_error(SearchError::Input { path, source });
+							continue;
+						}
+					};
+					let printed = printer.print_from(
+						&name,
+						reader,
+						&config.matcher,
+						update.line_number,
+						update.offset,
+					);
+					match printed {
+						Err(e @ SearchError::Output(_)) => return Err(e),
+						Err(e) => on_error(e),
+						Ok(_) => {}
+					}
+				}
+				Ok(None) => {}
+				// removed since the directory was walked, which log rotation does
+				Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+				Err(source) => on_error(SearchError::Input { path, source }),
+			}
+		}
+	}
+}
 
-		Ok(Config {
-			query,
-			filename,
-			ignore_case,
-		})
+// the files named on the command line, and the ones in the directories that are now there
+fn watched_files(config: &Config, names: &[String]) -> Vec<PathBuf> {
+	let mut files = Vec::new();
+	for name in names {
+		let path = Path::new(name);
+		if path.is_dir() {
+			files.extend(walk::walk(path, &config.walk).files);
+		} else {
+			files.push(path.to_path_buf());
+		}
 	}
+	files
 }
 
-// Box<dyn Error> returns a type that implements Error
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-	let contents = fs::read_to_string(config.filename)?;
+// the number of selected lines
+fn search_input<W: Write>(
+	config: &Config,
+	printer: &mut Printer<W>,
+	input: &Input,
+) -> Result<usize, SearchError> {
+	let failed = |source| SearchError::Input {
+		path: PathBuf::from(&input.name),
+		source,
+	};
+	match &input.path {
+		None => {
+			let reader = Decoder::new(io::stdin().lock()).map_err(failed)?;
+			printer.print(&input.name, reader, &config.matcher)
+		}
+		Some(path) => {
+			// read through a buffer instead of loading the whole file into memory
+			let file = File::open(path).map_err(failed)?;
+			let reader = Decoder::new(BufReader::new(file)).map_err(failed)?;
+			printer.print(&input.name, reader, &config.matcher)
+		}
+	}
+}
 
-	let results = if config.ignore_case {
-		search_case_insensitive(&config.query, &contents)
-	} else {
-		search(&config.query, &contents)
+// the files of a directory that the search reads
+fn directory_files(config: &Config, dir: &Path) -> io::Result<walk::Walk> {
+	// only searches for literal patterns, where a file without a match prints nothing,
+	// can skip the files the index rules out
+	let literal = match &config.matcher {
+		Matcher::Word(inner) | Matcher::Line(inner) => inner.as_ref(),
+		matcher => matcher,
 	};
+	let literal = matches!(
+		literal,
+		Matcher::Literal(_) | Matcher::CaseInsensitive(_) | Matcher::Multi(_)
+	);
+	let silent_misses = !config.invert
+		&& !matches!(
+			config.mode,
+			OutputMode::Count | OutputMode::FilesWithoutMatch
+		);
 
-	for line in results {
-		println!("{}", line);
+	if !config.index || !literal || !silent_misses {
+		return Ok(walk::walk(dir, &config.walk));
 	}
+	let index = index::Index::load(dir).map_err(|e| {
+		io::Error::new(
+			e.kind(),
+			format!(
+				"can't read the index ({}), build it with 'minigrep index'",
+				e
+			),
+		)
+	})?;
+	// the index holds the text itself, so the patterns are looked up as they are searched for
+	let patterns: Vec<String> = match config.multiline {
+		true => config
+			.patterns
+			.iter()
+			.map(|p| multiline::unescape(p))
+			.collect(),
+		false => config.patterns.clone(),
+	};
+	Ok(index.candidates(dir, &config.walk, &patterns))
+}
 
+// builds or updates the trigram index of every directory, for searches with --index
+// the files that can't be read are handed to `on_error` and left out, like a search
+// leaves them out
+pub fn run_index(
+	dirs: &[String],
+	options: &walk::WalkOptions,
+	on_error: &mut dyn FnMut(SearchError),
+) -> Result<(), SearchError> {
+	let mut failures = 0;
+	for dir in dirs {
+		let (_, summary) =
+			index::Index::update(Path::new(dir), options).map_err(|source| SearchError::Index {
+				dir: PathBuf::from(dir),
+				source,
+			})?;
+		println!(
+			"{}: indexed {} files ({} updated, {} removed)",
+			dir, summary.files, summary.updated, summary.removed
+		);
+		failures += summary.errors.len();
+		for (path, source) in summary.errors {
+			on_error(SearchError::Input { path, source });
+		}
+	}
+	if failures > 0 {
+		return Err(SearchError::Inputs { failed: failures });
+	}
 	Ok(())
 }
 
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-	let mut results = Vec::new();
+// rewrites the matches in every input, or prints the changes as a diff
+// on success, tells whether anything was replaced
+fn run_replace(
+	config: &Config,
+	template: &Template,
+	inputs: &[Input],
+	mut failures: usize,
+	on_error: &mut dyn FnMut(SearchError),
+) -> Result<bool, SearchError> {
+	let stdout = io::stdout();
+	let mut out = stdout.lock();
+	let mut replaced = false;
 
-	for line in contents.lines() {
-		if line.contains(query) {
-			results.push(line);
+	for input in inputs {
+		match replace_input(config, template, input, &mut out) {
+			Ok(changed) => replaced |= changed,
+			Err(source) => {
+				on_error(SearchError::Input {
+					path: PathBuf::from(&input.name),
+					source,
+				});
+				failures += 1;
+			}
 		}
 	}
+	out.flush().map_err(SearchError::Output)?;
 
-	results
+	if failures > 0 {
+		return Err(SearchError::Inputs { failed: failures });
+	}
+	Ok(replaced)
 }
 
-pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-	let query = query.to_lowercase();
+// whether anything in the input changed
+fn replace_input<W: Write>(
+	config: &Config,
+	template: &Template,
+	input: &Input,
+	out: &mut W,
+) -> io::Result<bool> {
+	// the whole input is needed to rewrite it
+	let mut bytes = Vec::new();
+	match &input.path {
+		None => io::stdin().lock().read_to_end(&mut bytes)?,
+		Some(path) => File::open(path)?.read_to_end(&mut bytes)?,
+	};
+	// binary files are left alone, unless they are searched as text
+	// the NUL bytes of utf-16 text are gone once it is decoded
+	let text = replace::Text::decode(&bytes);
+	let binary = match &text {
+		Ok(text) => text.contents.contains('\0'),
+		Err(_) => bytes.contains(&0),
+	};
+	if binary && config.binary_files != BinaryFiles::Text {
+		return Ok(false);
+	}
+	let text = text?;
+
+	let (replaced, changes) = replace::replace_all(&config.matcher, template, &text.contents);
+	if changes.is_empty() {
+		return Ok(false);
+	}
+	match &input.path {
+		Some(path) if config.in_place => {
+			replace::write_in_place(path, &text.encode(&replaced), config.backup)?
+		}
+		_ => out.write_all(replace::diff(&input.name, &changes).as_bytes())?,
+	}
+	Ok(true)
+}
+
+// the whole of `contents` is scanned for the query at once, and only the lines
+// around a hit are looked at, so lines without a match cost next to nothing
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+	// every line contains the empty query, and no line contains a newline
+	if query.is_empty() {
+		return contents.lines().collect();
+	}
+	if query.contains('\n') {
+		return Vec::new();
+	}
+
+	let bytes = contents.as_bytes();
+	let finder = memmem::Finder::new(query.as_bytes());
 	let mut results = Vec::new();
+	let mut position = 0;
 
-	for line in contents.lines() {
-		if line.to_lowercase().contains(&query) {
+	while let Some(found) = finder.find(&bytes[position..]) {
+		let hit = position + found;
+		let start = memmem::memrchr(b'\n', &bytes[..hit]).map_or(0, |newline| newline + 1);
+		let (end, next) = match memmem::memchr(b'\n', &bytes[hit..]) {
+			Some(newline) => (hit + newline, hit + newline + 1),
+			None => (bytes.len(), bytes.len() + 1),
+		};
+		// like str::lines, the '\r' of a "\r\n" isn't part of the line
+		let line = &contents[start..end];
+		let line = match next <= bytes.len() {
+			true => line.strip_suffix('\r').unwrap_or(line),
+			false => line,
+		};
+
+		if hit + query.len() <= start + line.len() {
 			results.push(line);
+			position = next;
+		} else {
+			// the hit reaches into the line terminator, so look further on this line
+			position = hit + 1;
+		}
+		if position > bytes.len() {
+			break;
 		}
 	}
 
 	results
 }
 
+// compares case folded characters as it goes, instead of lowercasing every line
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+	let matcher = Matcher::case_insensitive(query);
+	contents
+		.lines()
+		.filter(|line| matcher.is_match(line))
+		.collect()
+}
+
+// the regex carries its own case sensitivity
+pub fn search_regex<'a>(regex: &Regex, contents: &'a str) -> Vec<&'a str> {
+	contents
+		.lines()
+		.filter(|line| regex.is_match(line))
+		.collect()
+}
+
 // Test Driven Development, TDD:
 // 1. write a test that fails and run it to make sure it fails for the reason you expect
 // 2. write or modify just enough code to make the new test pass
@@ -96,6 +565,21 @@ Duct tape.";
 		assert_eq!(vec!["safe, fast, productive."], search(query, contents));
 	}
 
+	#[test]
+	fn case_sensitive_line_boundaries() {
+		let contents = "first a\r\nsecond\nthird a\r\n\na\r";
+		let lines = |query| search(query, contents);
+		let old = |query| -> Vec<&str> {
+			contents
+				.lines()
+				.filter(|line| line.contains(query))
+				.collect()
+		};
+		for query in ["a", "a\r", "second", "d", "", "t a", "\n", "x"] {
+			assert_eq!(old(query), lines(query), "{:?}", query);
+		}
+	}
+
 	#[test]
 	fn case_insensitive() {
 		let query = "rUsT";
@@ -110,4 +594,36 @@ Trust me.";
 			search_case_insensitive(query, contents)
 		);
 	}
+
+	#[test]
+	fn case_insensitive_unicode() {
+		let contents = "\
+Grüße aus der STRASSE
+ΟΔΥΣΣΕΥΣ
+plain";
+
+		assert_eq!(
+			vec!["Grüße aus der STRASSE"],
+			search_case_insensitive("straße", contents)
+		);
+		assert_eq!(
+			vec!["ΟΔΥΣΣΕΥΣ"],
+			search_case_insensitive("οδυσσευς", contents)
+		);
+	}
+
+	#[test]
+	fn regex() {
+		let regex = Regex::new("^(ERROR|WARN)\\b").unwrap();
+		let contents = "\
+ERROR: disk full
+INFO: all good
+WARNING: not a level
+WARN: low memory";
+
+		assert_eq!(
+			vec!["ERROR: disk full", "WARN: low memory"],
+			search_regex(&regex, contents)
+		);
+	}
 }
@@ -1,5 +1,11 @@
 // we want to run the program with
-// cargo run <search-string> <file-name>
+// cargo run -- [options] <search-string> [file-name...]
+// or, to search for several strings at once, with -e <search-string> or -f <pattern-file>
+// cargo run -- [options] -e <search-string>... [file-name...]
+// a file name can also be a directory, which is searched recursively
+// without file names, or with "-", standard input is searched
+// cargo run -- index [dir...] builds the index that searches with --index use
+// cargo run -- --help lists the options
 
 // separation of concerns for binary projects:
 // - split your program into a main.rs and a lib.rs and move your program’s logic to lib.rs
@@ -16,21 +22,65 @@
 use std::env;
 use std::process;
 
-use chapter_12::Config;
+use chapter_12::args::{self, Command};
+use chapter_12::error::{self, SearchError};
+use chapter_12::filter;
+
+// like grep, 0 means something was found and 1 that nothing was, so scripts can
+// treat anything above 1 as an error; each kind of error has its own status
+fn exit_with(e: SearchError) -> ! {
+	let (problem, status) = match &e {
+		SearchError::MissingPattern
+		| SearchError::UnknownOption(_)
+		| SearchError::MissingValue { .. }
+		| SearchError::UnexpectedValue(_)
+		| SearchError::InvalidValue { .. }
+		| SearchError::InvalidArgument { .. } => ("Problem parsing arguments", 2),
+		SearchError::ArgumentFile { .. } => ("Problem reading arguments", 3),
+		SearchError::ConfigFile { .. } => ("Problem with the config file", 4),
+		SearchError::Pattern { .. } => ("Invalid pattern", 5),
+		SearchError::Input { .. } | SearchError::Inputs { .. } => ("Search error", 6),
+		SearchError::Index { .. } => ("Index error", 7),
+		SearchError::Output(_) => ("Output error", 8),
+	};
+	eprintln!("{}: {}", problem, error::describe(&e));
+	process::exit(status);
+}
+
+// an input that can't be searched is reported as soon as it fails, like grep does
+fn report(e: SearchError) {
+	eprintln!("{}", error::describe(&e));
+}
 
 fn main() {
 	// return an iterator over the command line arguments
 	let args: Vec<String> = env::args().collect();
 
-	// return inner value of Ok
-	// else call the code in the closure
-	let config = Config::new(&args).unwrap_or_else(|err| {
-		eprintln!("Problem parsing arguments: {}", err);
-		process::exit(1);
-	});
+	let config = match args::parse(&args) {
+		Ok(Command::Search(config)) => config,
+		Ok(Command::Index { dirs, walk }) => match chapter_12::run_index(&dirs, &walk, &mut report)
+		{
+			Ok(()) => process::exit(0),
+			Err(e) => exit_with(e),
+		},
+		Ok(Command::Help) => {
+			print!("{}", args::help());
+			process::exit(0);
+		}
+		Ok(Command::Version) => {
+			println!("{}", args::version());
+			process::exit(0);
+		}
+		Ok(Command::TypeList) => {
+			print!("{}", filter::type_list());
+			process::exit(0);
+		}
+		Err(e) => exit_with(e),
+	};
 
-	if let Err(e) = chapter_12::run(config) {
-		eprintln!("Application error: {}", e);
-		process::exit(1);
+	match chapter_12::run(config, &mut report) {
+		Ok(true) => process::exit(0),
+		Ok(false) => process::exit(1),
+		Err(e) => exit_with(e),
 	}
 }
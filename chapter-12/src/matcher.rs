@@ -0,0 +1,243 @@
+// finding where the query matches, not just whether it does
+
+use std::ops::Range;
+
+use crate::aho_corasick::AhoCorasick;
+use crate::casefold;
+use crate::fuzzy::Fuzzy;
+use crate::regex::{self, Regex};
+
+// the compiled form of the query
+#[derive(Debug, Clone)]
+pub enum Matcher {
+	Literal(String),
+	// the query is stored case folded
+	CaseInsensitive(String),
+	Regex(Regex),
+	// any of several literal patterns, found in a single pass
+	Multi(AhoCorasick),
+	// substrings within an edit distance of the query (--fuzzy)
+	Fuzzy(Fuzzy),
+	// only matches of the inner matcher that are whole words (-w)
+	Word(Box<Matcher>),
+	// only matches of the inner matcher that are the whole line (-x)
+	Line(Box<Matcher>),
+}
+
+impl Matcher {
+	pub fn literal(query: &str) -> Matcher {
+		Matcher::Literal(query.to_string())
+	}
+
+	pub fn case_insensitive(query: &str) -> Matcher {
+		Matcher::CaseInsensitive(casefold::fold_str(query))
+	}
+
+	pub fn multi<S: AsRef<str>>(patterns: &[S], ignore_case: bool) -> Matcher {
+		Matcher::Multi(AhoCorasick::new(patterns, ignore_case))
+	}
+
+	pub fn is_match(&self, line: &str) -> bool {
+		self.find_at(line, 0).is_some()
+	}
+
+	// the byte range of the first match in `line` which starts at or after `start`
+	pub fn find_at(&self, line: &str, start: usize) -> Option<(usize, usize)> {
+		match self {
+			Matcher::Literal(query) => line[start..]
+				.find(query.as_str())
+				.map(|i| (start + i, start + i + query.len())),
+			Matcher::CaseInsensitive(query) => casefold::find(line, query, start),
+			Matcher::Regex(regex) => regex.find_at(line, start),
+			Matcher::Multi(patterns) => patterns.find_at(line, start),
+			Matcher::Fuzzy(fuzzy) => fuzzy.find_at(line, start).map(|(s, e, _)| (s, e)),
+			Matcher::Word(inner) => find_word(inner, line, start),
+			Matcher::Line(inner) => find_line(inner, line, start),
+		}
+	}
+
+	// the byte ranges of the first match at or after `start` and of its capturing groups,
+	// which only regular expressions have
+	pub fn captures_at(&self, line: &str, start: usize) -> Option<Vec<Option<(usize, usize)>>> {
+		match self {
+			Matcher::Regex(regex) => regex.captures_at(line, start),
+			_ => self.find_at(line, start).map(|span| vec![Some(span)]),
+		}
+	}
+
+	// how far a match found by this matcher is from the query, which only fuzzy matches can be
+	pub fn distance(&self, matched: &str) -> Option<usize> {
+		match self {
+			Matcher::Fuzzy(fuzzy) => Some(fuzzy.distance(matched)),
+			Matcher::Word(inner) | Matcher::Line(inner) => inner.distance(matched),
+			_ => None,
+		}
+	}
+}
+
+// the first match of `inner` that isn't directly preceded or followed by a word character,
+// like grep -w does it
+// this is exact for a literal query, whose matches from a given start all have the same length,
+// and close for several of them, where only the longest one at a start is tried;
+// regular expressions check the boundaries inside the pattern instead
+fn find_word(inner: &Matcher, line: &str, start: usize) -> Option<(usize, usize)> {
+	let mut position = start;
+	loop {
+		let (s, e) = inner.find_at(line, position)?;
+		if !regex::word_char_before(line, s) && !regex::word_char_after(line, e) {
+			return Some((s, e));
+		}
+		// try again from the next character
+		position = s + line[s..].chars().next()?.len_utf8();
+	}
+}
+
+// the first match of `inner` that starts at the start of a line and ends at its end,
+// like grep -x does it; `line` is normally a single line, but can have several (-U)
+fn find_line(inner: &Matcher, line: &str, start: usize) -> Option<(usize, usize)> {
+	let mut position = start;
+	loop {
+		let (s, e) = inner.find_at(line, position)?;
+		let rest = &line[e..];
+		let starts_line = s == 0 || line[..s].ends_with('\n');
+		let ends_line = rest.is_empty() || rest.starts_with('\n') || rest.starts_with("\r\n");
+		if starts_line && ends_line {
+			return Some((s, e));
+		}
+		// only a match at the start of the next line can still fit
+		position = s + line[s..].find('\n')? + 1;
+	}
+}
+
+// a single match of the query, with everything needed to point at it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match<'a> {
+	// the 1-based number of the line the match is on
+	pub line_number: usize,
+	// the 1-based byte column of the start of the match, like ripgrep reports it
+	pub column: usize,
+	// the offset of the start of the match from the beginning of the contents
+	pub byte_offset: usize,
+	// the byte range of the match within `line`
+	pub span: Range<usize>,
+	// the whole line, without its line terminator
+	pub line: &'a str,
+	// the edit distance from the query, for fuzzy matches
+	pub distance: Option<usize>,
+}
+
+impl<'a> Match<'a> {
+	pub fn as_str(&self) -> &'a str {
+		&self.line[self.span.clone()]
+	}
+}
+
+// the lines of `contents` with the byte offset at which each one starts
+// "\n" and "\r\n" terminators are removed, like str::lines does
+pub fn lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+	contents.split_inclusive('\n').scan(0, |offset, line| {
+		let start = *offset;
+		*offset += line.len();
+		let line = line.strip_suffix('\n').unwrap_or(line);
+		let line = line.strip_suffix('\r').unwrap_or(line);
+		Some((start, line))
+	})
+}
+
+// every match of `matcher` in `contents`, line by line and from left to right
+pub fn find_matches<'m, 'a>(
+	matcher: &'m Matcher,
+	contents: &'a str,
+) -> impl Iterator<Item = Match<'a>> + 'm
+where
+	'a: 'm,
+{
+	lines(contents)
+		.enumerate()
+		.flat_map(move |(i, (offset, line))| {
+			LineMatches::new(matcher, line).map(move |(start, end)| Match {
+				line_number: i + 1,
+				column: start + 1,
+				byte_offset: offset + start,
+				span: start..end,
+				line,
+				distance: matcher.distance(&line[start..end]),
+			})
+		})
+}
+
+// the non-overlapping matches within a single line, or within a whole text
+// when matches can span lines
+pub struct LineMatches<'m, 'a> {
+	matcher: &'m Matcher,
+	line: &'a str,
+	position: usize,
+}
+
+impl<'m, 'a> LineMatches<'m, 'a> {
+	pub fn new(matcher: &'m Matcher, line: &'a str) -> LineMatches<'m, 'a> {
+		LineMatches {
+			matcher,
+			line,
+			position: 0,
+		}
+	}
+}
+
+impl<'m, 'a> Iterator for LineMatches<'m, 'a> {
+	type Item = (usize, usize);
+
+	fn next(&mut self) -> Option<(usize, usize)> {
+		if self.position > self.line.len() {
+			return None;
+		}
+		let (start, end) = self.matcher.find_at(self.line, self.position)?;
+
+		// step over empty matches so they aren't found forever
+		self.position = if start == end {
+			end + self.line[end..].chars().next().map_or(1, char::len_utf8)
+		} else {
+			end
+		};
+
+		Some((start, end))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matches_carry_their_position() {
+		let matcher = Matcher::literal("ab");
+		let contents = "xab\r\nno\nab ab";
+		let matches: Vec<_> = find_matches(&matcher, contents).collect();
+
+		let positions: Vec<_> = matches
+			.iter()
+			.map(|m| (m.line_number, m.column, m.byte_offset))
+			.collect();
+		assert_eq!(vec![(1, 2, 1), (3, 1, 8), (3, 4, 11)], positions);
+		assert_eq!("ab ab", matches[2].line);
+		assert_eq!("ab", matches[2].as_str());
+	}
+
+	#[test]
+	fn whole_words_and_lines() {
+		let word = Matcher::Word(Box::new(Matcher::literal("duct")));
+		assert!(!word.is_match("safe, fast, productive."));
+		assert_eq!(Some((12, 16)), word.find_at("productive, duct tape", 0));
+
+		let line = Matcher::Line(Box::new(Matcher::case_insensitive("pick three.")));
+		assert!(line.is_match("Pick three."));
+		assert!(!line.is_match("Pick three. Or four."));
+	}
+
+	#[test]
+	fn case_insensitive_spans_point_into_the_line() {
+		let matcher = Matcher::case_insensitive("RUST");
+		assert_eq!(Some((6, 10)), matcher.find_at("Ünï rUsT", 0));
+		assert_eq!(None, matcher.find_at("rus", 0));
+	}
+}
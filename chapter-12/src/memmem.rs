@@ -0,0 +1,226 @@
+// fast substring search over a whole buffer
+//
+// instead of comparing the needle at every position, the search first looks for
+// its rarest byte, guessed from how often bytes show up in typical text and code,
+// and only compares the whole needle where that byte is found; in text where most
+// of the buffer can't match, this skips through it about as fast as memchr would
+//
+// when the rare byte turns out not to be rare in this haystack, and too many
+// candidates fail, the search switches to boyer-moore-horspool: it compares the
+// last byte of the window first, and on a mismatch shifts the window by how far
+// that byte is from the end of the needle, skipping up to the needle's length at once
+
+// how common each byte is in text, higher is more common; a rough ranking in the
+// spirit of the one the memchr crate uses
+fn frequency(byte: u8) -> u8 {
+	match byte {
+		b' ' => 255,
+		b'e' => 250,
+		b't' | b'a' | b'o' | b'i' | b'n' | b's' | b'r' => 240,
+		b'h' | b'l' | b'd' | b'c' | b'u' | b'm' => 220,
+		b'\n' | b'\t' | b'p' | b'f' | b'g' | b'w' | b'y' | b'b' => 200,
+		b',' | b'.' | b'(' | b')' | b';' | b'_' | b'=' | b'"' | b':' | b'/' | b'-' => 190,
+		b'v' | b'k' | b'0'..=b'9' => 170,
+		b'A'..=b'Z' => 150,
+		b'x' | b'j' | b'q' | b'z' => 120,
+		b'{' | b'}' | b'[' | b']' | b'<' | b'>' | b'\'' | b'*' | b'&' | b'#' => 110,
+		0x80..=0xFF => 40,
+		_ => 60,
+	}
+}
+
+const WORD: usize = std::mem::size_of::<usize>();
+const LOW_BITS: usize = usize::MAX / 255;
+const HIGH_BITS: usize = LOW_BITS << 7;
+
+// whether any byte of `word` is zero, checking all of them with a few word operations
+fn has_zero_byte(word: usize) -> bool {
+	word.wrapping_sub(LOW_BITS) & !word & HIGH_BITS != 0
+}
+
+fn read_word(bytes: &[u8]) -> usize {
+	let mut word = [0; WORD];
+	word.copy_from_slice(&bytes[..WORD]);
+	usize::from_ne_bytes(word)
+}
+
+// the position of the first `byte` in `haystack`
+// compares a whole word of bytes at a time, and only looks at single bytes
+// in the word that has the byte, and at the end of the haystack
+pub fn memchr(byte: u8, haystack: &[u8]) -> Option<usize> {
+	let repeated = LOW_BITS * byte as usize;
+	let mut i = 0;
+	while i + 2 * WORD <= haystack.len() {
+		let a = read_word(&haystack[i..]) ^ repeated;
+		let b = read_word(&haystack[i + WORD..]) ^ repeated;
+		if has_zero_byte(a) || has_zero_byte(b) {
+			break;
+		}
+		i += 2 * WORD;
+	}
+	haystack[i..]
+		.iter()
+		.position(|&b| b == byte)
+		.map(|found| i + found)
+}
+
+// the position of the last `byte` in `haystack`
+pub fn memrchr(byte: u8, haystack: &[u8]) -> Option<usize> {
+	let repeated = LOW_BITS * byte as usize;
+	let mut end = haystack.len();
+	while end >= 2 * WORD {
+		let a = read_word(&haystack[end - WORD..]) ^ repeated;
+		let b = read_word(&haystack[end - 2 * WORD..]) ^ repeated;
+		if has_zero_byte(a) || has_zero_byte(b) {
+			break;
+		}
+		end -= 2 * WORD;
+	}
+	haystack[..end].iter().rposition(|&b| b == byte)
+}
+
+// how many times `byte` is in `haystack`
+pub fn memcount(byte: u8, haystack: &[u8]) -> usize {
+	let mut count = 0;
+	let mut rest = haystack;
+	while let Some(i) = memchr(byte, rest) {
+		count += 1;
+		rest = &rest[i + 1..];
+	}
+	count
+}
+
+#[derive(Debug, Clone)]
+pub struct Finder {
+	needle: Vec<u8>,
+	// where the rarest byte of the needle is
+	rare: usize,
+	// how far the window moves when its last byte is a given byte
+	shift: [usize; 256],
+}
+
+impl Finder {
+	pub fn new(needle: &[u8]) -> Finder {
+		let rare = (0..needle.len())
+			.min_by_key(|&i| frequency(needle[i]))
+			.unwrap_or(0);
+
+		let mut shift = [needle.len().max(1); 256];
+		if let Some((_, init)) = needle.split_last() {
+			for (i, &byte) in init.iter().enumerate() {
+				shift[byte as usize] = needle.len() - 1 - i;
+			}
+		}
+
+		Finder {
+			needle: needle.to_vec(),
+			rare,
+			shift,
+		}
+	}
+
+	// the position of the first occurrence of the needle in `haystack`
+	pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+		let len = self.needle.len();
+		if len == 0 {
+			return Some(0);
+		}
+		if haystack.len() < len {
+			return None;
+		}
+
+		let rare_byte = self.needle[self.rare];
+		let mut position = self.rare;
+		// a failed candidate costs about a comparison of the needle, while the prefilter
+		// pays off by skipping bytes; once candidates cost more than they skip, give up on it
+		let mut skipped = 0;
+		let mut failed = 0;
+		while let Some(found) = memchr(rare_byte, &haystack[position..]) {
+			let start = position + found - self.rare;
+			if start + len > haystack.len() {
+				return None;
+			}
+			if haystack[start..start + len] == self.needle[..] {
+				return Some(start);
+			}
+			skipped += found;
+			failed += 1;
+			position += found + 1;
+			if failed > 16 && failed * len > skipped {
+				return self.horspool(&haystack[start + 1..]).map(|i| start + 1 + i);
+			}
+		}
+		None
+	}
+
+	fn horspool(&self, haystack: &[u8]) -> Option<usize> {
+		let len = self.needle.len();
+		let last = self.needle[len - 1];
+		let mut start = 0;
+		while start + len <= haystack.len() {
+			let byte = haystack[start + len - 1];
+			if byte == last && haystack[start..start + len - 1] == self.needle[..len - 1] {
+				return Some(start);
+			}
+			start += self.shift[byte as usize];
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_like_str_find() {
+		let haystacks = [
+			"",
+			"a",
+			"hello world",
+			"aaaaaaaaab",
+			"abababababc",
+			"zzzz needle zzz",
+		];
+		let needles = ["", "a", "b", "ab", "abc", "needle", "world", "aab", "zzz"];
+		for haystack in haystacks {
+			for needle in needles {
+				let finder = Finder::new(needle.as_bytes());
+				assert_eq!(
+					haystack.find(needle),
+					finder.find(haystack.as_bytes()),
+					"{:?} in {:?}",
+					needle,
+					haystack
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn finds_bytes_a_word_at_a_time() {
+		let haystack = b"0123456789abcdef0123456789abcdefXYZ\n";
+		for start in 0..haystack.len() {
+			let haystack = &haystack[start..];
+			for byte in [b'0', b'9', b'f', b'X', b'\n', b'!'] {
+				assert_eq!(
+					haystack.iter().position(|&b| b == byte),
+					memchr(byte, haystack)
+				);
+				assert_eq!(
+					haystack.iter().rposition(|&b| b == byte),
+					memrchr(byte, haystack)
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn switches_to_horspool_when_the_rare_byte_is_common() {
+		// 'q' is rare in text, but not in this haystack
+		let mut haystack = "qa".repeat(1000);
+		haystack.push_str("qz");
+		let finder = Finder::new(b"qz");
+		assert_eq!(Some(2000), finder.find(haystack.as_bytes()));
+	}
+}
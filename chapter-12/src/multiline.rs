@@ -0,0 +1,112 @@
+// matches that can span several lines (-U)
+//
+// normally every line is searched on its own, so a pattern can never match a
+// newline; in multiline mode the whole input is searched at once instead, and a
+// match selects every line it touches, from the one it starts on to the one it ends on
+//
+// a literal pattern spells a newline as \n (and a backslash as \\), like a regular
+// expression does; ^ and $ still match at the start and end of lines
+
+use std::ops::Range;
+
+use crate::matcher::{LineMatches, Matcher};
+use crate::memmem;
+
+// a match and the lines it spans
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultilineMatch {
+	// the 1-based numbers of the first and last line the match touches
+	pub start_line: usize,
+	pub end_line: usize,
+	// the byte range of the match within the whole text
+	pub span: Range<usize>,
+}
+
+// every match in `text`, in order
+pub fn find_all(matcher: &Matcher, text: &str) -> Vec<MultilineMatch> {
+	// the line that `counted` is on, counting lines as we go instead of from the start
+	let mut line = 1;
+	let mut counted = 0;
+	let mut line_of = |offset: usize| {
+		line += memmem::memcount(b'\n', &text.as_bytes()[counted..offset]);
+		counted = offset;
+		line
+	};
+
+	// the whole text is searched like a single line that has newlines in it
+	LineMatches::new(matcher, text)
+		.map(|(start, end)| {
+			// a match that ends with a newline ends on the line of that newline
+			let last = if end > start { end - 1 } else { start };
+			MultilineMatch {
+				start_line: line_of(start),
+				end_line: line_of(last),
+				span: start..end,
+			}
+		})
+		.collect()
+}
+
+// a literal pattern with its \n turned into newlines and \\ into backslashes,
+// every other backslash is kept as it is
+pub fn unescape(pattern: &str) -> String {
+	let mut unescaped = String::with_capacity(pattern.len());
+	let mut chars = pattern.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			unescaped.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('n') => unescaped.push('\n'),
+			Some('\\') => unescaped.push('\\'),
+			Some(other) => {
+				unescaped.push('\\');
+				unescaped.push(other);
+			}
+			None => unescaped.push('\\'),
+		}
+	}
+	unescaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::regex::{Options, Regex};
+
+	fn lines(matches: Vec<MultilineMatch>) -> Vec<(usize, usize)> {
+		matches.iter().map(|m| (m.start_line, m.end_line)).collect()
+	}
+
+	#[test]
+	fn matches_span_lines() {
+		let text = "fn main() {}\n\nfn search(\n\tquery: &str,\n) -> bool {\n";
+		let literal = Matcher::literal("search(\n\tquery");
+		assert_eq!(vec![(3, 4)], lines(find_all(&literal, text)));
+
+		let options = Options {
+			multi_line: true,
+			..Options::default()
+		};
+		let signature = Matcher::Regex(Regex::with_options(r"^fn \w+\([^)]*\)", options).unwrap());
+		let found = find_all(&signature, text);
+		assert_eq!(vec![(1, 1), (3, 5)], lines(found.clone()));
+		assert_eq!(
+			"fn search(\n\tquery: &str,\n)",
+			&text[found[1].span.clone()]
+		);
+
+		// a match ending with its newline doesn't reach into the next line
+		assert_eq!(
+			vec![(1, 1), (2, 2)],
+			lines(find_all(&Matcher::literal("{}\n"), "{}\n{}\n"))
+		);
+	}
+
+	#[test]
+	fn literals_spell_newlines_as_escapes() {
+		assert_eq!("search(\n    query", unescape(r"search(\n    query"));
+		assert_eq!(r"a\n \d\", unescape(r"a\\n \d\"));
+	}
+}
@@ -0,0 +1,158 @@
+// a small worker pool for searching many files at once
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+
+// how far the output got, which the workers wait on
+#[derive(Default)]
+struct Progress {
+	// the number of results that were output
+	output: usize,
+	stopped: bool,
+}
+
+// runs `job` on every item using `threads` worker threads
+// `output` receives the results in the order of the items, each one as soon as
+// it and all the ones before it are done, so the output is the same as running
+// the jobs one after the other
+// when `output` returns false, no more jobs are started and the remaining results
+// are dropped
+//
+// a result waits in memory until it is output, so a job is only started when it is
+// less than `threads` items ahead of the output; a slow job holds the others back
+// instead of letting the results after it pile up
+pub fn ordered_map<'a, T, R, J, O>(items: &'a [T], threads: usize, job: J, mut output: O)
+where
+	T: Sync,
+	R: Send,
+	J: Fn(&'a T) -> R + Sync,
+	O: FnMut(R) -> bool,
+{
+	let threads = threads.clamp(1, items.len().max(1));
+	// the index of the next item a worker should take
+	let next = AtomicUsize::new(0);
+	let progress = (Mutex::new(Progress::default()), Condvar::new());
+	let (tx, rx) = mpsc::channel();
+
+	// scoped threads can borrow `items` and `job`, since they are
+	// guaranteed to finish before the scope returns
+	thread::scope(|scope| {
+		for _ in 0..threads {
+			let tx = tx.clone();
+			let next = &next;
+			let job = &job;
+			let (lock, ready) = &progress;
+			scope.spawn(move || loop {
+				let i = next.fetch_add(1, Ordering::Relaxed);
+				if i >= items.len() {
+					break;
+				}
+				// the items before this one were all taken, so the output gets here
+				let mut waiting = lock.lock().unwrap();
+				while !waiting.stopped && i >= waiting.output + threads {
+					waiting = ready.wait(waiting).unwrap();
+				}
+				if waiting.stopped {
+					break;
+				}
+				drop(waiting);
+				// the receiver only goes away if the main thread panicked
+				if tx.send((i, job(&items[i]))).is_err() {
+					break;
+				}
+			});
+		}
+		// the workers hold the remaining senders, so the loop below ends with them
+		drop(tx);
+
+		// results that arrived before the ones that come earlier in the output
+		let mut waiting = BTreeMap::new();
+		let mut expected = 0;
+		let (lock, ready) = &progress;
+		for (i, result) in rx {
+			waiting.insert(i, result);
+			while let Some(result) = waiting.remove(&expected) {
+				let go_on = output(result);
+				expected += 1;
+				let mut progress = lock.lock().unwrap();
+				progress.output = expected;
+				progress.stopped = !go_on;
+				ready.notify_all();
+				if !go_on {
+					// dropping the receiver stops the workers at their next result
+					next.store(items.len(), Ordering::Relaxed);
+					return;
+				}
+			}
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keeps_the_order_of_the_items() {
+		let items: Vec<u64> = (0..50).collect();
+		let mut results = Vec::new();
+		ordered_map(
+			&items,
+			4,
+			|&n| {
+				// make later items finish first
+				thread::sleep(std::time::Duration::from_micros(50 - n));
+				n * 2
+			},
+			|n| {
+				results.push(n);
+				true
+			},
+		);
+
+		assert_eq!(items.iter().map(|n| n * 2).collect::<Vec<_>>(), results);
+	}
+
+	#[test]
+	fn doesnt_run_too_far_ahead_of_the_output() {
+		let items: Vec<u64> = (0..100).collect();
+		let written = AtomicUsize::new(0);
+		let ahead = AtomicUsize::new(0);
+		ordered_map(
+			&items,
+			4,
+			|&n| {
+				let n = n as usize;
+				ahead.fetch_max(n - written.load(Ordering::SeqCst), Ordering::SeqCst);
+				// the first item of every ten is slow
+				if n.is_multiple_of(10) {
+					thread::sleep(std::time::Duration::from_millis(2));
+				}
+			},
+			|()| {
+				written.fetch_add(1, Ordering::SeqCst);
+				true
+			},
+		);
+		assert!(ahead.load(Ordering::SeqCst) < 4);
+	}
+
+	#[test]
+	fn stops_when_the_output_does() {
+		let items: Vec<u64> = (0..1000).collect();
+		let mut results = Vec::new();
+		ordered_map(
+			&items,
+			4,
+			|&n| n,
+			|n| {
+				results.push(n);
+				n < 10
+			},
+		);
+
+		assert_eq!((0..=10).collect::<Vec<_>>(), results);
+	}
+}
@@ -0,0 +1,754 @@
+// formats search results, including the context around each match
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use crate::error::SearchError;
+use crate::json;
+use crate::matcher::Matcher;
+use crate::searcher::{self, SearchOptions, Sink, SinkLine, SinkMatch};
+
+// ansi escape sequences, in the colors grep uses by default
+const MATCH_COLOR: &str = "\x1b[1;31m";
+const FILENAME_COLOR: &str = "\x1b[35m";
+const LINE_NUMBER_COLOR: &str = "\x1b[32m";
+const SEPARATOR_COLOR: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+// when to highlight matches (--color)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorChoice {
+	// only when writing to a terminal
+	#[default]
+	Auto,
+	Always,
+	Never,
+}
+
+impl ColorChoice {
+	pub fn parse(value: &str) -> Option<ColorChoice> {
+		match value {
+			"auto" => Some(ColorChoice::Auto),
+			"always" => Some(ColorChoice::Always),
+			"never" => Some(ColorChoice::Never),
+			_ => None,
+		}
+	}
+
+	// whether output written to `stream` should be colored
+	pub fn should_color<T: IsTerminal>(self, stream: &T) -> bool {
+		match self {
+			ColorChoice::Auto => stream.is_terminal(),
+			ColorChoice::Always => true,
+			ColorChoice::Never => false,
+		}
+	}
+}
+
+// what is printed for every input
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OutputMode {
+	// the selected lines and their context
+	#[default]
+	Lines,
+	// the number of selected lines (-c)
+	Count,
+	// the name of inputs with a selected line (-l)
+	FilesWithMatches,
+	// the name of inputs without any selected line (-L)
+	FilesWithoutMatch,
+	// nothing at all, only the exit status tells whether something matched (-q)
+	Quiet,
+	// one json object per line for every event: the begin and end of each input,
+	// each selected and context line and a summary at the very end (--json)
+	Json,
+}
+
+// what to do with binary inputs, the ones with a NUL byte (--binary-files)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BinaryFiles {
+	// search them, but only say whether they match instead of printing lines
+	#[default]
+	Binary,
+	// search them like text (-a)
+	Text,
+	// don't search them, as if nothing in them matched
+	WithoutMatch,
+}
+
+impl BinaryFiles {
+	pub fn parse(value: &str) -> Option<BinaryFiles> {
+		match value {
+			"binary" => Some(BinaryFiles::Binary),
+			"text" => Some(BinaryFiles::Text),
+			"without-match" => Some(BinaryFiles::WithoutMatch),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PrintOptions {
+	pub mode: OutputMode,
+	// select the lines that don't match instead of the ones that do (-v)
+	pub invert: bool,
+	// stop reading an input after this many selected lines (-m)
+	pub max_count: Option<usize>,
+	// prefix lines with the name of their input
+	pub with_filename: bool,
+	// lines of context to print before and after each match (-B and -A)
+	pub before: usize,
+	pub after: usize,
+	// prefix lines with their number (-n)
+	pub line_number: bool,
+	// prefix matching lines with the column of the first match (--column)
+	pub column: bool,
+	// highlight matches, file names and line numbers with ansi colors
+	pub color: bool,
+	pub binary_files: BinaryFiles,
+	// matches can span lines, and every line they touch is printed (-U)
+	pub multiline: bool,
+}
+
+// totals over every input, for the json summary
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+	pub searched: usize,
+	pub with_matches: usize,
+	pub selected_lines: usize,
+}
+
+pub struct Printer<W: Write> {
+	out: W,
+	options: PrintOptions,
+	// whether a group of lines was printed, so the next group needs a separator
+	printed_group: bool,
+	stats: Stats,
+}
+
+impl<W: Write> Printer<W> {
+	pub fn new(out: W, options: PrintOptions) -> Printer<W> {
+		Printer {
+			out,
+			options,
+			printed_group: false,
+			stats: Stats::default(),
+		}
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+
+	// ends the output, which only the json format needs
+	pub fn finish(&mut self) -> io::Result<()> {
+		if self.options.mode == OutputMode::Json {
+			writeln!(
+				self.out,
+				r#"{{"type":"summary","searched":{},"with_matches":{},"matched_lines":{}}}"#,
+				self.stats.searched, self.stats.with_matches, self.stats.selected_lines
+			)?;
+		}
+		self.out.flush()
+	}
+
+	// writes out what another printer buffered, for example on a worker thread,
+	// as if it had been printed here
+	pub fn append(&mut self, other: Printer<Vec<u8>>) -> io::Result<()> {
+		let with_context = self.options.mode != OutputMode::Json
+			&& (self.options.before > 0 || self.options.after > 0);
+		if with_context && self.printed_group && other.printed_group {
+			self.write_colored(SEPARATOR_COLOR, "--")?;
+			writeln!(self.out)?;
+		}
+		self.printed_group |= other.printed_group;
+		self.stats.searched += other.stats.searched;
+		self.stats.with_matches += other.stats.with_matches;
+		self.stats.selected_lines += other.stats.selected_lines;
+		self.out.write_all(&other.out)
+	}
+
+	// prints the lines read from `reader` that `matcher` selects, with their context
+	// after the file name and line number, selected lines are marked with ':'
+	// and context lines with '-', like grep does
+	//
+	// a binary input's lines aren't printed, a match is reported as
+	// "Binary file NAME matches" instead
+	//
+	// returns the number of selected lines
+	// an input that can't be read fails with SearchError::Input, and output that can't
+	// be written with SearchError::Output
+	pub fn print<R: BufRead>(
+		&mut self,
+		name: &str,
+		reader: R,
+		matcher: &Matcher,
+	) -> Result<usize, SearchError> {
+		self.print_from(name, reader, matcher, 0, 0)
+	}
+
+	// like print, for the part of an input that starts after `line_number` lines and
+	// `offset` bytes, such as the lines appended to a watched file
+	pub fn print_from<R: BufRead>(
+		&mut self,
+		name: &str,
+		reader: R,
+		matcher: &Matcher,
+		line_number: usize,
+		offset: usize,
+	) -> Result<usize, SearchError> {
+		let mode = self.options.mode;
+		// only printed lines have context
+		let lines = matches!(mode, OutputMode::Lines | OutputMode::Json);
+		let options = SearchOptions {
+			invert: self.options.invert,
+			before: if lines { self.options.before } else { 0 },
+			after: if lines { self.options.after } else { 0 },
+			max_count: self.options.max_count,
+			binary_files: self.options.binary_files,
+			multiline: self.options.multiline,
+		};
+
+		if mode == OutputMode::Json {
+			writeln!(
+				self.out,
+				r#"{{"type":"begin","path":{}}}"#,
+				json::string(name)
+			)
+			.map_err(SearchError::Output)?;
+		}
+
+		let mut sink = PrintSink {
+			printer: self,
+			name,
+			matcher,
+			binary: false,
+			started: false,
+			write_error: None,
+		};
+		let result =
+			searcher::search_lines(matcher, &options, reader, line_number, offset, &mut sink);
+		let PrintSink {
+			binary,
+			write_error,
+			..
+		} = sink;
+		if let Some(e) = write_error {
+			return Err(SearchError::Output(e));
+		}
+		let count = result.map_err(|source| SearchError::Input {
+			path: PathBuf::from(name),
+			source,
+		})?;
+		self.print_end(name, count, binary)
+			.map_err(SearchError::Output)?;
+
+		self.stats.searched += 1;
+		self.stats.selected_lines += count;
+		if count > 0 {
+			self.stats.with_matches += 1;
+		}
+		Ok(count)
+	}
+
+	// what is printed once an input was searched
+	fn print_end(&mut self, name: &str, count: usize, binary: bool) -> io::Result<()> {
+		match self.options.mode {
+			OutputMode::Lines if binary && count > 0 => {
+				writeln!(self.out, "Binary file {} matches", name)?;
+			}
+			OutputMode::Count => {
+				if self.options.with_filename {
+					self.write_colored(FILENAME_COLOR, name)?;
+					self.write_colored(SEPARATOR_COLOR, ":")?;
+				}
+				writeln!(self.out, "{}", count)?;
+			}
+			OutputMode::FilesWithMatches if count > 0 => {
+				self.write_colored(FILENAME_COLOR, name)?;
+				writeln!(self.out)?;
+			}
+			OutputMode::FilesWithoutMatch if count == 0 => {
+				self.write_colored(FILENAME_COLOR, name)?;
+				writeln!(self.out)?;
+			}
+			OutputMode::Json => writeln!(
+				self.out,
+				r#"{{"type":"end","path":{},"matched_lines":{}}}"#,
+				json::string(name),
+				count
+			)?,
+			_ => {}
+		}
+		Ok(())
+	}
+
+	// `selected` is false for context lines
+	// the matches come with the line, since one that spans lines can't be found on a
+	// single line again
+	fn print_line(
+		&mut self,
+		name: &str,
+		line: &SinkLine,
+		selected: bool,
+		matcher: &Matcher,
+	) -> io::Result<()> {
+		let separator = if selected { ":" } else { "-" };
+		// context and inverted lines have nothing to point at
+		let matches: Vec<SinkMatch> = line.matches().collect();
+
+		if self.options.mode == OutputMode::Json {
+			return self.print_json_line(name, line, selected, &matches, matcher);
+		}
+		let (line_number, line) = (line.line_number, line.text);
+
+		if self.options.with_filename {
+			self.write_colored(FILENAME_COLOR, name)?;
+			self.write_colored(SEPARATOR_COLOR, separator)?;
+		}
+		if self.options.line_number {
+			self.write_colored(LINE_NUMBER_COLOR, &line_number.to_string())?;
+			self.write_colored(SEPARATOR_COLOR, separator)?;
+		}
+		let first = matches.first();
+		if let (true, Some(first)) = (self.options.column, first) {
+			self.write_colored(LINE_NUMBER_COLOR, &(first.start + 1).to_string())?;
+			self.write_colored(SEPARATOR_COLOR, separator)?;
+		}
+		// fuzzy matches are prefixed with the edit distance of each of them, labeled
+		// so it can't be taken for a line number or column
+		let distances: Vec<String> = matches
+			.iter()
+			.filter_map(|m| matcher.distance(&line[m.start..m.end]))
+			.map(|distance| distance.to_string())
+			.collect();
+		if !distances.is_empty() {
+			let label = format!("distance={}", distances.join(","));
+			self.write_colored(LINE_NUMBER_COLOR, &label)?;
+			self.write_colored(SEPARATOR_COLOR, separator)?;
+		}
+
+		if !self.options.color || matches.is_empty() {
+			return writeln!(self.out, "{}", line);
+		}
+
+		// every match on the line is highlighted, not only the first one
+		let mut written = 0;
+		for m in &matches {
+			if m.start == m.end {
+				continue;
+			}
+			write!(self.out, "{}", &line[written..m.start])?;
+			self.write_colored(MATCH_COLOR, &line[m.start..m.end])?;
+			written = m.end;
+		}
+		writeln!(self.out, "{}", &line[written..])
+	}
+
+	fn print_json_line(
+		&mut self,
+		name: &str,
+		line: &SinkLine,
+		selected: bool,
+		matches: &[SinkMatch],
+		matcher: &Matcher,
+	) -> io::Result<()> {
+		let (line_number, offset, line) = (line.line_number, line.byte_offset, line.text);
+		let path = json::string(name);
+		let text = json::string(line);
+		if !selected {
+			return writeln!(
+				self.out,
+				r#"{{"type":"context","path":{},"line_number":{},"absolute_offset":{},"text":{}}}"#,
+				path, line_number, offset, text
+			);
+		}
+
+		// a line selected by -v is a match without submatches
+		let column = matches.first().map_or(0, |m| m.start) + 1;
+		let submatches: Vec<String> = matches
+			.iter()
+			.map(|m| {
+				let distance = matcher
+					.distance(&line[m.start..m.end])
+					.map_or(String::new(), |d| format!(r#","distance":{}"#, d));
+				// the lines a match spans, which only multiline matches can
+				let lines = match self.options.multiline {
+					true => format!(
+						r#","start_line_number":{},"end_line_number":{}"#,
+						m.start_line, m.end_line
+					),
+					false => String::new(),
+				};
+				format!(
+					r#"{{"match":{},"start":{},"end":{}{}{}}}"#,
+					json::string(&line[m.start..m.end]),
+					m.start,
+					m.end,
+					lines,
+					distance
+				)
+			})
+			.collect();
+		writeln!(
+			self.out,
+			r#"{{"type":"match","path":{},"line_number":{},"column":{},"absolute_offset":{},"text":{},"submatches":[{}]}}"#,
+			path,
+			line_number,
+			column,
+			offset,
+			text,
+			submatches.join(",")
+		)
+	}
+
+	fn write_colored(&mut self, color: &str, text: &str) -> io::Result<()> {
+		if self.options.color {
+			write!(self.out, "{}{}{}", color, text, RESET)
+		} else {
+			write!(self.out, "{}", text)
+		}
+	}
+}
+
+// prints the lines of a single input as the search hands them over
+struct PrintSink<'p, 'a, W: Write> {
+	printer: &'p mut Printer<W>,
+	name: &'a str,
+	matcher: &'a Matcher,
+	binary: bool,
+	// whether a line of this input was printed
+	started: bool,
+	// the output failed, so the search was stopped
+	write_error: Option<io::Error>,
+}
+
+impl<W: Write> PrintSink<'_, '_, W> {
+	fn print_line(&mut self, line: &SinkLine, selected: bool) -> io::Result<bool> {
+		let printer = &mut *self.printer;
+		let with_context = printer.options.mode != OutputMode::Json
+			&& (printer.options.before > 0 || printer.options.after > 0);
+		// the first group of an input is separated from the last one of the input before
+		if !self.started && with_context && printer.printed_group {
+			printer.write_colored(SEPARATOR_COLOR, "--")?;
+			writeln!(printer.out)?;
+		}
+		self.started = true;
+		printer.printed_group = true;
+		printer.print_line(self.name, line, selected, self.matcher)?;
+		Ok(true)
+	}
+
+	// the search only fails when reading does, so a failed write stops it
+	// and is kept to be told apart
+	fn written(&mut self, result: io::Result<bool>) -> io::Result<bool> {
+		result.or_else(|e| {
+			self.write_error = Some(e);
+			Ok(false)
+		})
+	}
+}
+
+impl<W: Write> Sink for PrintSink<'_, '_, W> {
+	fn matched(&mut self, line: &SinkLine) -> io::Result<bool> {
+		match self.printer.options.mode {
+			OutputMode::Count => Ok(true),
+			OutputMode::Lines | OutputMode::Json if !self.binary => {
+				let result = self.print_line(line, true);
+				self.written(result)
+			}
+			// a single selected line settles the others and binary inputs, so the rest isn't read
+			_ => Ok(false),
+		}
+	}
+
+	fn context(&mut self, line: &SinkLine) -> io::Result<bool> {
+		if self.binary {
+			return Ok(true);
+		}
+		let result = self.print_line(line, false);
+		self.written(result)
+	}
+
+	fn context_break(&mut self) -> io::Result<bool> {
+		if self.printer.options.mode == OutputMode::Json || self.binary {
+			return Ok(true);
+		}
+		let result = self
+			.printer
+			.write_colored(SEPARATOR_COLOR, "--")
+			.and_then(|()| writeln!(self.printer.out))
+			.map(|()| true);
+		self.written(result)
+	}
+
+	fn binary(&mut self, _offset: usize) -> io::Result<bool> {
+		self.binary = true;
+		Ok(true)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::path::Path;
+
+	fn print(options: PrintOptions, contents: &str, matcher: &Matcher) -> String {
+		let options = PrintOptions {
+			with_filename: true,
+			..options
+		};
+		let mut printer = Printer::new(Vec::new(), options);
+		printer.print("f", contents.as_bytes(), matcher).unwrap();
+		String::from_utf8(printer.out).unwrap()
+	}
+
+	#[test]
+	fn merges_overlapping_and_touching_windows() {
+		let options = PrintOptions {
+			before: 1,
+			after: 1,
+			..PrintOptions::default()
+		};
+		let contents = "0\n1 x\n2 x\n3\n4\n5 x\n6\n7\n8\n9 x";
+		assert_eq!(
+			"f-0\nf:1 x\nf:2 x\nf-3\nf-4\nf:5 x\nf-6\n--\nf-8\nf:9 x\n",
+			print(options, contents, &Matcher::literal("x"))
+		);
+	}
+
+	#[test]
+	fn prints_separators_between_groups() {
+		let contents = "\
+one
+two match
+three
+four
+five
+six match
+seven";
+		let options = PrintOptions {
+			before: 1,
+			..PrintOptions::default()
+		};
+		assert_eq!(
+			"f-one\nf:two match\n--\nf-five\nf:six match\n",
+			print(options, contents, &Matcher::literal("match"))
+		);
+	}
+
+	#[test]
+	fn prints_line_numbers_and_columns() {
+		let options = PrintOptions {
+			after: 1,
+			line_number: true,
+			column: true,
+			..PrintOptions::default()
+		};
+		assert_eq!(
+			"f:1:3:a match\nf-2-next\n",
+			print(options, "a match\nnext", &Matcher::literal("match"))
+		);
+	}
+
+	#[test]
+	fn highlights_every_match() {
+		let options = PrintOptions {
+			color: true,
+			..PrintOptions::default()
+		};
+		let mut printer = Printer::new(Vec::new(), options);
+		printer
+			.print(
+				"f",
+				"Rust and rust".as_bytes(),
+				&Matcher::case_insensitive("RUST"),
+			)
+			.unwrap();
+
+		let output = String::from_utf8(printer.out).unwrap();
+		assert_eq!("\x1b[1;31mRust\x1b[0m and \x1b[1;31mrust\x1b[0m\n", output);
+	}
+
+	#[test]
+	fn inverts_and_stops_after_max_count() {
+		let options = PrintOptions {
+			invert: true,
+			max_count: Some(2),
+			..PrintOptions::default()
+		};
+		assert_eq!(
+			"f:a\nf:c\n",
+			print(options, "a\nb\nc\nd", &Matcher::literal("b"))
+		);
+	}
+
+	#[test]
+	fn counts_and_lists_files() {
+		let matcher = Matcher::literal("x");
+		let count = PrintOptions {
+			mode: OutputMode::Count,
+			..PrintOptions::default()
+		};
+		assert_eq!("f:2\n", print(count, "x\ny\nx", &matcher));
+
+		let with_matches = PrintOptions {
+			mode: OutputMode::FilesWithMatches,
+			..PrintOptions::default()
+		};
+		assert_eq!("f\n", print(with_matches.clone(), "y\nx", &matcher));
+		assert_eq!("", print(with_matches, "y", &matcher));
+
+		let without_match = PrintOptions {
+			mode: OutputMode::FilesWithoutMatch,
+			..PrintOptions::default()
+		};
+		assert_eq!("f\n", print(without_match, "y", &matcher));
+	}
+
+	#[test]
+	fn json_events() {
+		let options = PrintOptions {
+			mode: OutputMode::Json,
+			before: 1,
+			..PrintOptions::default()
+		};
+		let mut printer = Printer::new(Vec::new(), options);
+		printer
+			.print("f", "a \"b\"\nx b b".as_bytes(), &Matcher::literal("b"))
+			.unwrap();
+		printer.finish().unwrap();
+
+		let output = String::from_utf8(printer.out).unwrap();
+		let lines: Vec<&str> = output.lines().collect();
+		assert_eq!(
+			vec![
+				r#"{"type":"begin","path":"f"}"#,
+				r#"{"type":"match","path":"f","line_number":1,"column":4,"absolute_offset":0,"text":"a \"b\"","submatches":[{"match":"b","start":3,"end":4}]}"#,
+				r#"{"type":"match","path":"f","line_number":2,"column":3,"absolute_offset":6,"text":"x b b","submatches":[{"match":"b","start":2,"end":3},{"match":"b","start":4,"end":5}]}"#,
+				r#"{"type":"end","path":"f","matched_lines":2}"#,
+				r#"{"type":"summary","searched":1,"with_matches":1,"matched_lines":2}"#,
+			],
+			lines
+		);
+
+		// lines selected by -v are matches too, with nothing to point at
+		let inverted = PrintOptions {
+			mode: OutputMode::Json,
+			invert: true,
+			..PrintOptions::default()
+		};
+		let mut printer = Printer::new(Vec::new(), inverted);
+		printer
+			.print(
+				"f",
+				"a
+b"
+				.as_bytes(),
+				&Matcher::literal("b"),
+			)
+			.unwrap();
+		let output = String::from_utf8(printer.out).unwrap();
+		assert_eq!(
+			r#"{"type":"match","path":"f","line_number":1,"column":1,"absolute_offset":0,"text":"a","submatches":[]}"#,
+			output.lines().nth(1).unwrap()
+		);
+
+		// buffers printed on other threads are joined without the context separator
+		let json = PrintOptions {
+			mode: OutputMode::Json,
+			before: 1,
+			..PrintOptions::default()
+		};
+		let mut printer = Printer::new(Vec::new(), json.clone());
+		for name in ["a", "b"] {
+			let mut buffer = Printer::new(Vec::new(), json.clone());
+			buffer
+				.print(
+					name,
+					"x
+b"
+					.as_bytes(),
+					&Matcher::literal("b"),
+				)
+				.unwrap();
+			printer.append(buffer).unwrap();
+		}
+		let output = String::from_utf8(printer.out).unwrap();
+		assert!(output.lines().all(|line| line.starts_with('{')));
+	}
+
+	#[test]
+	fn binary_and_invalid_utf8() {
+		let contents: &[u8] = b"caf\xe9 ok\nbad \xff byte\n";
+		let mut printer = Printer::new(Vec::new(), PrintOptions::default());
+		printer
+			.print("f", contents, &Matcher::literal("ok"))
+			.unwrap();
+		assert_eq!("caf\u{fffd} ok\n", String::from_utf8(printer.out).unwrap());
+
+		let binary: &[u8] = b"text\0more\nmatch\nmatch\n";
+		let output = |binary_files| {
+			let options = PrintOptions {
+				binary_files,
+				..PrintOptions::default()
+			};
+			let mut printer = Printer::new(Vec::new(), options);
+			let count = printer
+				.print("f", binary, &Matcher::literal("match"))
+				.unwrap();
+			(count, String::from_utf8(printer.out).unwrap())
+		};
+		assert_eq!(
+			(1, "Binary file f matches\n".to_string()),
+			output(BinaryFiles::Binary)
+		);
+		assert_eq!((2, "match\nmatch\n".to_string()), output(BinaryFiles::Text));
+		assert_eq!((0, String::new()), output(BinaryFiles::WithoutMatch));
+	}
+
+	#[test]
+	fn fuzzy_distances() {
+		let matcher = Matcher::Fuzzy(crate::fuzzy::Fuzzy::new("timeout", 1, false));
+		let options = PrintOptions {
+			line_number: true,
+			column: true,
+			..PrintOptions::default()
+		};
+		assert_eq!(
+			"f:1:1:distance=0:timeout\nf:3:6:distance=1,0:read timout timeout\n",
+			print(options, "timeout\nok\nread timout timeout\ntime", &matcher)
+		);
+	}
+
+	#[test]
+	fn failed_writes_and_reads_are_told_apart() {
+		struct Closed;
+		impl Write for Closed {
+			fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+				Err(io::ErrorKind::BrokenPipe.into())
+			}
+			fn flush(&mut self) -> io::Result<()> {
+				Ok(())
+			}
+		}
+		struct Unreadable;
+		impl io::Read for Unreadable {
+			fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+				Err(io::ErrorKind::PermissionDenied.into())
+			}
+		}
+
+		let matcher = Matcher::literal("a");
+		let mut closed = Printer::new(Closed, PrintOptions::default());
+		let error = closed
+			.print("f", "a\na\n".as_bytes(), &matcher)
+			.unwrap_err();
+		assert!(matches!(error, SearchError::Output(e) if e.kind() == io::ErrorKind::BrokenPipe));
+
+		let mut printer = Printer::new(Vec::new(), PrintOptions::default());
+		let reader = io::BufReader::new(Unreadable);
+		let error = printer.print("f", reader, &matcher).unwrap_err();
+		assert!(matches!(error, SearchError::Input { path, .. } if path == Path::new("f")));
+	}
+}
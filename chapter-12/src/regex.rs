@@ -0,0 +1,1026 @@
+// a small regular expression engine for minigrep's --regex mode
+//
+// supported syntax:
+// - literals and escapes: a, \., \\, \n, \t, \r
+// - any character: .
+// - character classes: [abc], [a-z], [^0-9], \d, \w, \s and their negations \D, \W, \S
+// - anchors: ^, $, \b, \B
+// - alternation: a|b
+// - groups: (abc) and non-capturing (?:abc)
+// - repetition: *, +, ?, {n}, {n,}, {n,m} and their lazy forms (*?, +?, ...)
+//
+// the pattern is parsed into a syntax tree, compiled into a small program
+// and executed by a pike vm, which runs every alternative in lockstep
+// this keeps matching linear in the length of the text, unlike a backtracking engine
+// which can take exponential time on patterns like (a*)*b
+
+use std::error::Error as StdError;
+use std::fmt;
+
+// repetitions are expanded into copies of the repeated program,
+// so we cap the counts to keep the compiled program small
+const MAX_REPEAT: u32 = 1000;
+// nested repetitions multiply, so the whole program is capped as well
+const MAX_PROGRAM: usize = 100_000;
+// parsing and compiling recurse into nested nodes, so the nesting is capped
+// to keep them from overflowing the stack
+const MAX_NESTING: usize = 250;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+	message: String,
+	position: usize,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"invalid regex at position {}: {}",
+			self.position, self.message
+		)
+	}
+}
+
+impl StdError for Error {}
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+	pattern: String,
+	program: Vec<Inst>,
+	groups: usize,
+	ignore_case: bool,
+	multi_line: bool,
+}
+
+// how a pattern is compiled
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+	// letters match regardless of their case
+	pub ignore_case: bool,
+	// a match can't be preceded or followed by a word character (grep -w)
+	pub whole_word: bool,
+	// a match has to span the whole text (grep -x)
+	pub whole_line: bool,
+	// ^ and $ also match right after and before a newline, for text with several lines
+	pub multi_line: bool,
+}
+
+impl Regex {
+	pub fn new(pattern: &str) -> Result<Regex, Error> {
+		Regex::with_options(pattern, Options::default())
+	}
+
+	// letters match regardless of their case
+	pub fn new_ignore_case(pattern: &str) -> Result<Regex, Error> {
+		let options = Options {
+			ignore_case: true,
+			..Options::default()
+		};
+		Regex::with_options(pattern, options)
+	}
+
+	pub fn with_options(pattern: &str, options: Options) -> Result<Regex, Error> {
+		let mut parser = Parser::new(pattern);
+		let mut ast = parser.parse()?;
+		let ignore_case = options.ignore_case;
+		let multi_line = options.multi_line;
+
+		// the pattern is wrapped in assertions, which unlike checking the boundaries
+		// of a match afterwards also finds the longer or shorter alternatives that fit
+		let group = |ast| Node::Group(Box::new(ast), None);
+		if options.whole_line {
+			ast = Node::Concat(vec![
+				Node::Look(Look::Start),
+				group(ast),
+				Node::Look(Look::End),
+			]);
+		} else if options.whole_word {
+			ast = Node::Concat(vec![
+				Node::Look(Look::NotAfterWord),
+				group(ast),
+				Node::Look(Look::NotBeforeWord),
+			]);
+		}
+
+		let mut compiler = Compiler {
+			program: Vec::new(),
+			ignore_case,
+		};
+		compiler.emit(Inst::Save(0));
+		compiler.compile(&ast)?;
+		compiler.emit(Inst::Save(1));
+		compiler.emit(Inst::Match);
+
+		Ok(Regex {
+			pattern: pattern.to_string(),
+			program: compiler.program,
+			groups: parser.groups + 1,
+			ignore_case,
+			multi_line,
+		})
+	}
+
+	pub fn as_str(&self) -> &str {
+		&self.pattern
+	}
+
+	pub fn ignore_case(&self) -> bool {
+		self.ignore_case
+	}
+
+	pub fn is_match(&self, text: &str) -> bool {
+		self.find(text).is_some()
+	}
+
+	// the byte range of the leftmost match
+	pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+		self.find_at(text, 0)
+	}
+
+	// like find, but the match can't start before `start`
+	// anchors and word boundaries still see the text before `start`
+	pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+		let slots = self.slots_at(text, start)?;
+		Some((slots[0]?, slots[1]?))
+	}
+
+	// the byte ranges of the whole match (group 0) and of every capturing group
+	// groups which didn't take part in the match are None
+	pub fn captures_at(&self, text: &str, start: usize) -> Option<Vec<Option<(usize, usize)>>> {
+		let slots = self.slots_at(text, start)?;
+		let groups = slots
+			.chunks(2)
+			.map(|pair| match (pair[0], pair[1]) {
+				(Some(s), Some(e)) => Some((s, e)),
+				_ => None,
+			})
+			.collect();
+		Some(groups)
+	}
+
+	fn slots_at(&self, text: &str, start: usize) -> Option<Vec<Option<usize>>> {
+		let mut vm = Vm::new(self, text);
+		vm.run(start)
+	}
+}
+
+impl fmt::Display for Regex {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.pattern)
+	}
+}
+
+// syntax tree
+
+#[derive(Debug, Clone)]
+enum Node {
+	Empty,
+	Char(char),
+	Any,
+	Class(Class),
+	Look(Look),
+	Group(Box<Node>, Option<usize>),
+	Concat(Vec<Node>),
+	Alternate(Vec<Node>),
+	Repeat {
+		node: Box<Node>,
+		min: u32,
+		max: Option<u32>,
+		greedy: bool,
+	},
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Look {
+	Start,
+	End,
+	WordBoundary,
+	NotWordBoundary,
+	// no word character right before or after the position, used for whole words
+	NotAfterWord,
+	NotBeforeWord,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Perl {
+	Digit,
+	Word,
+	Space,
+}
+
+impl Perl {
+	fn matches(self, c: char) -> bool {
+		match self {
+			Perl::Digit => c.is_ascii_digit(),
+			Perl::Word => is_word_char(c),
+			Perl::Space => c.is_whitespace(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+	Range(char, char),
+	Perl(Perl, bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Class {
+	items: Vec<ClassItem>,
+	negated: bool,
+}
+
+impl Class {
+	fn perl(perl: Perl, negated: bool) -> Class {
+		Class {
+			items: vec![ClassItem::Perl(perl, negated)],
+			negated: false,
+		}
+	}
+
+	fn contains(&self, c: char) -> bool {
+		self.items.iter().any(|item| match *item {
+			ClassItem::Range(low, high) => low <= c && c <= high,
+			ClassItem::Perl(perl, negated) => perl.matches(c) != negated,
+		})
+	}
+
+	fn matches(&self, c: char, ignore_case: bool) -> bool {
+		let found = if ignore_case {
+			self.contains(c) || case_variants(c).any(|v| self.contains(v))
+		} else {
+			self.contains(c)
+		};
+		found != self.negated
+	}
+}
+
+pub(crate) fn is_word_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_'
+}
+
+pub(crate) fn word_char_before(text: &str, position: usize) -> bool {
+	text[..position]
+		.chars()
+		.next_back()
+		.is_some_and(is_word_char)
+}
+
+pub(crate) fn word_char_after(text: &str, position: usize) -> bool {
+	text[position..].chars().next().is_some_and(is_word_char)
+}
+
+// the single character simple case mappings of c
+// characters that map to several characters (like ß -> SS) are left out
+fn case_variants(c: char) -> impl Iterator<Item = char> {
+	let mut lower = c.to_lowercase();
+	let mut upper = c.to_uppercase();
+	let lower = match (lower.next(), lower.next()) {
+		(Some(l), None) if l != c => Some(l),
+		_ => None,
+	};
+	let upper = match (upper.next(), upper.next()) {
+		(Some(u), None) if u != c => Some(u),
+		_ => None,
+	};
+	lower.into_iter().chain(upper)
+}
+
+fn simple_fold(c: char) -> char {
+	let mut lower = c.to_lowercase();
+	match (lower.next(), lower.next()) {
+		(Some(l), None) => l,
+		_ => c,
+	}
+}
+
+// parser
+
+struct Parser {
+	chars: Vec<char>,
+	position: usize,
+	groups: usize,
+	// groups open around the position
+	depth: usize,
+	// how deeply nested the last parsed node is
+	height: usize,
+}
+
+impl Parser {
+	fn new(pattern: &str) -> Parser {
+		Parser {
+			chars: pattern.chars().collect(),
+			position: 0,
+			groups: 0,
+			depth: 0,
+			height: 0,
+		}
+	}
+
+	fn error<T>(&self, message: &str) -> Result<T, Error> {
+		Err(Error {
+			message: message.to_string(),
+			position: self.position,
+		})
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.chars.get(self.position).copied()
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.peek();
+		if c.is_some() {
+			self.position += 1;
+		}
+		c
+	}
+
+	fn eat(&mut self, expected: char) -> bool {
+		if self.peek() == Some(expected) {
+			self.position += 1;
+			true
+		} else {
+			false
+		}
+	}
+
+	fn parse(&mut self) -> Result<Node, Error> {
+		let node = self.parse_alternation()?;
+		if self.peek() == Some(')') {
+			return self.error("unmatched ')'");
+		}
+		Ok(node)
+	}
+
+	fn parse_alternation(&mut self) -> Result<Node, Error> {
+		let mut branches = vec![self.parse_concat()?];
+		let mut height = self.height;
+		while self.eat('|') {
+			branches.push(self.parse_concat()?);
+			height = height.max(self.height);
+		}
+		if branches.len() == 1 {
+			Ok(branches.pop().unwrap())
+		} else {
+			self.nest(height)?;
+			Ok(Node::Alternate(branches))
+		}
+	}
+
+	fn parse_concat(&mut self) -> Result<Node, Error> {
+		let mut nodes = Vec::new();
+		let mut height = 0;
+		while let Some(c) = self.peek() {
+			if c == '|' || c == ')' {
+				break;
+			}
+			let atom = self.parse_atom()?;
+			nodes.push(self.parse_repeat(atom)?);
+			height = height.max(self.height);
+		}
+		match nodes.len() {
+			0 => {
+				self.height = 1;
+				Ok(Node::Empty)
+			}
+			1 => Ok(nodes.pop().unwrap()),
+			_ => {
+				self.nest(height)?;
+				Ok(Node::Concat(nodes))
+			}
+		}
+	}
+
+	fn parse_repeat(&mut self, mut atom: Node) -> Result<Node, Error> {
+		loop {
+			let (min, max) = match self.peek() {
+				Some('*') => {
+					self.next();
+					(0, None)
+				}
+				Some('+') => {
+					self.next();
+					(1, None)
+				}
+				Some('?') => {
+					self.next();
+					(0, Some(1))
+				}
+				Some('{') => match self.parse_counts()? {
+					Some(counts) => counts,
+					None => return Ok(atom),
+				},
+				_ => return Ok(atom),
+			};
+			if let Node::Look(_) | Node::Empty = atom {
+				return self.error("repetition operator without anything to repeat");
+			}
+			let greedy = !self.eat('?');
+			self.nest(self.height)?;
+			atom = Node::Repeat {
+				node: Box::new(atom),
+				min,
+				max,
+				greedy,
+			};
+		}
+	}
+
+	// {n}, {n,} or {n,m}
+	// a '{' which doesn't start a valid count is a literal, like in most engines
+	fn parse_counts(&mut self) -> Result<Option<(u32, Option<u32>)>, Error> {
+		let start = self.position;
+		self.next();
+
+		let min = match self.parse_number() {
+			Some(min) => min,
+			None => {
+				self.position = start;
+				return Ok(None);
+			}
+		};
+		let max = if self.eat(',') {
+			self.parse_number()
+		} else {
+			Some(min)
+		};
+		if !self.eat('}') {
+			self.position = start;
+			return Ok(None);
+		}
+
+		if min > MAX_REPEAT || max.is_some_and(|max| max > MAX_REPEAT) {
+			return self.error("repetition count is too large");
+		}
+		if max.is_some_and(|max| max < min) {
+			return self.error("invalid repetition range");
+		}
+		Ok(Some((min, max)))
+	}
+
+	fn parse_number(&mut self) -> Option<u32> {
+		let start = self.position;
+		while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+			self.next();
+		}
+		if start == self.position {
+			return None;
+		}
+		let digits: String = self.chars[start..self.position].iter().collect();
+		digits.parse().ok().or(Some(u32::MAX))
+	}
+
+	// wraps a node of the given height in another one
+	fn nest(&mut self, height: usize) -> Result<(), Error> {
+		if height >= MAX_NESTING {
+			return self.error("pattern nested too deeply");
+		}
+		self.height = height + 1;
+		Ok(())
+	}
+
+	fn parse_atom(&mut self) -> Result<Node, Error> {
+		self.height = 1;
+		match self.next() {
+			Some('(') => self.parse_group(),
+			Some('[') => Ok(Node::Class(self.parse_class()?)),
+			Some('.') => Ok(Node::Any),
+			Some('^') => Ok(Node::Look(Look::Start)),
+			Some('$') => Ok(Node::Look(Look::End)),
+			Some('\\') => self.parse_escape(),
+			Some('*') | Some('+') | Some('?') => {
+				self.position -= 1;
+				self.error("repetition operator without anything to repeat")
+			}
+			Some(c) => Ok(Node::Char(c)),
+			None => self.error("unexpected end of pattern"),
+		}
+	}
+
+	fn parse_group(&mut self) -> Result<Node, Error> {
+		let index = if self.eat('?') {
+			if !self.eat(':') {
+				return self.error("unsupported group flag");
+			}
+			None
+		} else {
+			self.groups += 1;
+			Some(self.groups)
+		};
+
+		if self.depth >= MAX_NESTING {
+			return self.error("groups nested too deeply");
+		}
+		self.depth += 1;
+		let node = self.parse_alternation()?;
+		self.depth -= 1;
+		if !self.eat(')') {
+			return self.error("unclosed group");
+		}
+		self.nest(self.height)?;
+		Ok(Node::Group(Box::new(node), index))
+	}
+
+	fn parse_escape(&mut self) -> Result<Node, Error> {
+		match self.next() {
+			Some('b') => Ok(Node::Look(Look::WordBoundary)),
+			Some('B') => Ok(Node::Look(Look::NotWordBoundary)),
+			Some(_) => {
+				self.position -= 1;
+				match self.parse_escaped_char()? {
+					ClassItem::Range(c, _) => Ok(Node::Char(c)),
+					ClassItem::Perl(perl, negated) => Ok(Node::Class(Class::perl(perl, negated))),
+				}
+			}
+			None => self.error("pattern ends with a backslash"),
+		}
+	}
+
+	// the character after a backslash, both inside and outside of classes
+	fn parse_escaped_char(&mut self) -> Result<ClassItem, Error> {
+		let c = match self.next() {
+			Some(c) => c,
+			None => return self.error("pattern ends with a backslash"),
+		};
+		let item = match c {
+			'd' => ClassItem::Perl(Perl::Digit, false),
+			'D' => ClassItem::Perl(Perl::Digit, true),
+			'w' => ClassItem::Perl(Perl::Word, false),
+			'W' => ClassItem::Perl(Perl::Word, true),
+			's' => ClassItem::Perl(Perl::Space, false),
+			'S' => ClassItem::Perl(Perl::Space, true),
+			'n' => ClassItem::Range('\n', '\n'),
+			't' => ClassItem::Range('\t', '\t'),
+			'r' => ClassItem::Range('\r', '\r'),
+			c if c.is_alphanumeric() => {
+				self.position -= 1;
+				return self.error("unknown escape sequence");
+			}
+			c => ClassItem::Range(c, c),
+		};
+		Ok(item)
+	}
+
+	fn parse_class(&mut self) -> Result<Class, Error> {
+		let negated = self.eat('^');
+		let mut items = Vec::new();
+
+		// a ']' right after the opening bracket is a literal
+		if self.eat(']') {
+			items.push(ClassItem::Range(']', ']'));
+		}
+
+		loop {
+			let low = match self.next() {
+				Some(']') => break,
+				Some('\\') => self.parse_escaped_char()?,
+				Some(c) => ClassItem::Range(c, c),
+				None => return self.error("unclosed character class"),
+			};
+
+			// a '-' followed by ']' is a literal '-'
+			let is_range = self.peek() == Some('-')
+				&& self.chars.get(self.position + 1).is_some_and(|&c| c != ']');
+			let low_char = match low {
+				ClassItem::Range(c, _) if is_range => c,
+				item => {
+					items.push(item);
+					continue;
+				}
+			};
+
+			self.next();
+			let high_char = match self.next() {
+				Some('\\') => match self.parse_escaped_char()? {
+					ClassItem::Range(c, _) => c,
+					ClassItem::Perl(..) => return self.error("invalid range in character class"),
+				},
+				Some(c) => c,
+				None => return self.error("unclosed character class"),
+			};
+			if high_char < low_char {
+				return self.error("invalid range in character class");
+			}
+			items.push(ClassItem::Range(low_char, high_char));
+		}
+
+		Ok(Class { items, negated })
+	}
+}
+
+// compiler
+
+#[derive(Debug, Clone)]
+enum Inst {
+	Char(char),
+	Any,
+	Class(Class),
+	Look(Look),
+	Save(usize),
+	Split(usize, usize),
+	Jump(usize),
+	Match,
+}
+
+struct Compiler {
+	program: Vec<Inst>,
+	ignore_case: bool,
+}
+
+impl Compiler {
+	fn emit(&mut self, inst: Inst) -> usize {
+		self.program.push(inst);
+		self.program.len() - 1
+	}
+
+	fn patch(&mut self, at: usize, target: usize) {
+		match &mut self.program[at] {
+			Inst::Jump(to) => *to = target,
+			Inst::Split(_, second) => *second = target,
+			_ => unreachable!("only jumps and splits are patched"),
+		}
+	}
+
+	fn compile(&mut self, node: &Node) -> Result<(), Error> {
+		if self.program.len() > MAX_PROGRAM {
+			return Err(Error {
+				message: String::from("pattern too large"),
+				position: 0,
+			});
+		}
+		match node {
+			Node::Empty => {}
+			Node::Char(c) => {
+				let c = if self.ignore_case {
+					simple_fold(*c)
+				} else {
+					*c
+				};
+				self.emit(Inst::Char(c));
+			}
+			Node::Any => {
+				self.emit(Inst::Any);
+			}
+			Node::Class(class) => {
+				self.emit(Inst::Class(class.clone()));
+			}
+			Node::Look(look) => {
+				self.emit(Inst::Look(*look));
+			}
+			Node::Group(node, index) => match index {
+				Some(index) => {
+					self.emit(Inst::Save(index * 2));
+					self.compile(node)?;
+					self.emit(Inst::Save(index * 2 + 1));
+				}
+				None => self.compile(node)?,
+			},
+			Node::Concat(nodes) => {
+				for node in nodes {
+					self.compile(node)?;
+				}
+			}
+			Node::Alternate(branches) => {
+				// split L1, next
+				// L1: branch 1
+				//     jump end
+				// next: split L2, next ...
+				let mut jumps = Vec::new();
+				for (i, branch) in branches.iter().enumerate() {
+					if i + 1 < branches.len() {
+						let split = self.emit(Inst::Split(0, 0));
+						self.program[split] = Inst::Split(split + 1, 0);
+						self.compile(branch)?;
+						jumps.push(self.emit(Inst::Jump(0)));
+						let next = self.program.len();
+						self.patch(split, next);
+					} else {
+						self.compile(branch)?;
+					}
+				}
+				let end = self.program.len();
+				for jump in jumps {
+					self.patch(jump, end);
+				}
+			}
+			Node::Repeat {
+				node,
+				min,
+				max,
+				greedy,
+			} => {
+				for _ in 0..*min {
+					self.compile(node)?;
+				}
+				match max {
+					None => {
+						// L1: split L2, end
+						// L2: node
+						//     jump L1
+						let split = self.emit(Inst::Split(0, 0));
+						self.compile(node)?;
+						self.emit(Inst::Jump(split));
+						let end = self.program.len();
+						self.program[split] = self.split(split + 1, end, *greedy);
+					}
+					Some(max) => {
+						// every optional copy may be skipped to the very end
+						let mut splits = Vec::new();
+						for _ in *min..*max {
+							splits.push(self.emit(Inst::Split(0, 0)));
+							self.compile(node)?;
+						}
+						let end = self.program.len();
+						for split in splits {
+							self.program[split] = self.split(split + 1, end, *greedy);
+						}
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	// the first target of a split has priority
+	fn split(&self, body: usize, skip: usize, greedy: bool) -> Inst {
+		if greedy {
+			Inst::Split(body, skip)
+		} else {
+			Inst::Split(skip, body)
+		}
+	}
+}
+
+// pike vm
+//
+// every thread is a program counter plus its capture slots
+// threads are kept in priority order, so the first thread to reach Match
+// wins, which gives perl-like leftmost-first semantics
+
+struct Threads {
+	// the generation in which each pc was added, to add each pc at most once per step
+	seen: Vec<usize>,
+	list: Vec<(usize, Vec<Option<usize>>)>,
+}
+
+impl Threads {
+	fn new(size: usize) -> Threads {
+		Threads {
+			seen: vec![usize::MAX; size],
+			list: Vec::new(),
+		}
+	}
+}
+
+struct Vm<'r, 't> {
+	regex: &'r Regex,
+	text: &'t str,
+}
+
+impl<'r, 't> Vm<'r, 't> {
+	fn new(regex: &'r Regex, text: &'t str) -> Vm<'r, 't> {
+		Vm { regex, text }
+	}
+
+	fn run(&mut self, start: usize) -> Option<Vec<Option<usize>>> {
+		let size = self.regex.program.len();
+		let mut current = Threads::new(size);
+		let mut next = Threads::new(size);
+		let mut matched = None;
+		let mut position = start;
+		let mut step = 0;
+
+		loop {
+			// start a new thread at each position until something matched
+			// it has the lowest priority, since earlier starts are preferred
+			if matched.is_none() {
+				let slots = vec![None; self.regex.groups * 2];
+				self.add(&mut current, step, 0, position, slots);
+			}
+			if current.list.is_empty() && matched.is_some() {
+				break;
+			}
+
+			let c = self.text[position..].chars().next();
+			let next_position = position + c.map_or(0, char::len_utf8);
+			step += 1;
+
+			for (pc, slots) in std::mem::take(&mut current.list) {
+				let advance = match &self.regex.program[pc] {
+					Inst::Match => {
+						// lower priority threads are cut off
+						matched = Some(slots);
+						break;
+					}
+					Inst::Char(expected) => c.is_some_and(|c| self.char_matches(*expected, c)),
+					Inst::Any => c.is_some_and(|c| c != '\n'),
+					Inst::Class(class) => {
+						c.is_some_and(|c| class.matches(c, self.regex.ignore_case))
+					}
+					_ => unreachable!("only consuming instructions are kept as threads"),
+				};
+				if advance {
+					self.add(&mut next, step, pc + 1, next_position, slots);
+				}
+			}
+
+			std::mem::swap(&mut current, &mut next);
+			match c {
+				Some(_) => position = next_position,
+				None => break,
+			}
+		}
+
+		matched
+	}
+
+	fn char_matches(&self, expected: char, c: char) -> bool {
+		if self.regex.ignore_case {
+			simple_fold(c) == expected
+		} else {
+			c == expected
+		}
+	}
+
+	// follow every instruction which doesn't consume input
+	//
+	// with a stack of its own instead of recursion, since a long chain of optional
+	// repetitions would overflow the call stack; the stack is last in first out, so
+	// the second target of a split is pushed first to keep the priority order
+	fn add(
+		&self,
+		threads: &mut Threads,
+		step: usize,
+		pc: usize,
+		position: usize,
+		slots: Vec<Option<usize>>,
+	) {
+		let mut stack = vec![(pc, slots)];
+		while let Some((pc, mut slots)) = stack.pop() {
+			if threads.seen[pc] == step {
+				continue;
+			}
+			threads.seen[pc] = step;
+
+			match self.regex.program[pc] {
+				Inst::Jump(to) => stack.push((to, slots)),
+				Inst::Split(first, second) => {
+					stack.push((second, slots.clone()));
+					stack.push((first, slots));
+				}
+				Inst::Save(slot) => {
+					slots[slot] = Some(position);
+					stack.push((pc + 1, slots));
+				}
+				Inst::Look(look) => {
+					if self.look_matches(look, position) {
+						stack.push((pc + 1, slots));
+					}
+				}
+				_ => threads.list.push((pc, slots)),
+			}
+		}
+	}
+
+	fn look_matches(&self, look: Look, position: usize) -> bool {
+		match look {
+			Look::Start => {
+				position == 0 || (self.regex.multi_line && self.text[..position].ends_with('\n'))
+			}
+			Look::End => {
+				let rest = &self.text[position..];
+				rest.is_empty()
+					|| (self.regex.multi_line
+						&& (rest.starts_with('\n') || rest.starts_with("\r\n")))
+			}
+			Look::WordBoundary => self.at_word_boundary(position),
+			Look::NotWordBoundary => !self.at_word_boundary(position),
+			Look::NotAfterWord => !word_char_before(self.text, position),
+			Look::NotBeforeWord => !word_char_after(self.text, position),
+		}
+	}
+
+	fn at_word_boundary(&self, position: usize) -> bool {
+		word_char_before(self.text, position) != word_char_after(self.text, position)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+		Regex::new(pattern).unwrap().find(text)
+	}
+
+	#[test]
+	fn literals_and_classes() {
+		assert_eq!(Some((4, 7)), find("fox", "the fox"));
+		assert_eq!(Some((3, 6)), find("[0-9]+", "abc123def"));
+		assert_eq!(Some((0, 3)), find(r"\w+", "foo bar"));
+		assert_eq!(Some((1, 2)), find("[^a-c]", "ad"));
+		assert_eq!(None, find("x.z", "x\nz"));
+	}
+
+	#[test]
+	fn anchors_and_alternation() {
+		let re = Regex::new("^(ERROR|WARN)").unwrap();
+		assert!(re.is_match("ERROR: disk full"));
+		assert!(re.is_match("WARN: low memory"));
+		assert!(!re.is_match("INFO: ERROR later"));
+
+		assert_eq!(Some((0, 3)), find(r"\bcat\b", "cat catalog"));
+		assert_eq!(None, find(r"\bcat\b", "catalog"));
+		assert_eq!(Some((3, 3)), find("$", "abc"));
+	}
+
+	#[test]
+	fn repetition() {
+		assert_eq!(Some((0, 4)), find("a{2,}", "aaaa"));
+		assert_eq!(Some((0, 2)), find("a{2}", "aaaa"));
+		assert_eq!(Some((0, 3)), find("a{1,3}", "aaaa"));
+		assert_eq!(Some((0, 1)), find("a+?", "aaaa"));
+		assert_eq!(Some((0, 3)), find("(ab)*c", "abc"));
+		assert_eq!(Some((0, 4)), find("x{a}", "x{a}!"));
+	}
+
+	#[test]
+	fn leftmost_first() {
+		assert_eq!(Some((0, 1)), find("a|ab", "ab"));
+		assert_eq!(Some((0, 2)), find("ab|a", "ab"));
+		assert_eq!(Some((1, 3)), find("b+", "abb"));
+	}
+
+	#[test]
+	fn captures() {
+		let re = Regex::new(r"(\w+)@(\w+)?").unwrap();
+		let groups = re.captures_at("mail me@home", 0).unwrap();
+		assert_eq!(vec![Some((5, 12)), Some((5, 7)), Some((8, 12))], groups);
+	}
+
+	#[test]
+	fn ignore_case() {
+		let re = Regex::new_ignore_case("^rust[a-z]*").unwrap();
+		assert_eq!(Some((0, 5)), re.find("RuSTY nail"));
+	}
+
+	#[test]
+	fn whole_words_and_lines() {
+		let word = Options {
+			whole_word: true,
+			..Options::default()
+		};
+		let re = Regex::with_options("duct|product", word).unwrap();
+		assert_eq!(Some((0, 7)), re.find("product"));
+		assert_eq!(
+			None,
+			Regex::with_options("duct", word)
+				.unwrap()
+				.find("productive")
+		);
+
+		let line = Options {
+			whole_line: true,
+			..Options::default()
+		};
+		let re = Regex::with_options("a|ab", line).unwrap();
+		assert_eq!(Some((0, 2)), re.find("ab"));
+		assert_eq!(None, re.find("abc"));
+	}
+
+	#[test]
+	fn errors() {
+		assert!(Regex::new("(abc").is_err());
+		assert!(Regex::new("abc)").is_err());
+		assert!(Regex::new("[abc").is_err());
+		assert!(Regex::new("*a").is_err());
+		assert!(Regex::new("[z-a]").is_err());
+		assert!(Regex::new(r"\q").is_err());
+	}
+
+	#[test]
+	fn nested_repeats_are_capped() {
+		let error = Regex::new("(?:(?:a?){1000}){1000}").unwrap_err();
+		assert!(error.to_string().ends_with("pattern too large"));
+		assert!(Regex::new("((a{1000}){1000}){1000}").is_err());
+
+		// a long chain of optional copies is followed without recursion
+		let regex = Regex::new("(?:a?){1000}b").unwrap();
+		assert_eq!(Some((0, 4)), regex.find(&format!("{}b", "a".repeat(3))));
+	}
+
+	#[test]
+	fn deep_nesting_is_an_error() {
+		let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+
+		let error = Regex::new(&nested(5000)).unwrap_err();
+		assert!(error.to_string().ends_with("groups nested too deeply"));
+		assert!(Regex::new(&format!("a{}", "{1}".repeat(5000))).is_err());
+		assert!(Regex::new(&format!("{}*", nested(200)).repeat(2)).is_ok());
+
+		let regex = Regex::new(&nested(200)).unwrap();
+		assert_eq!(Some((1, 2)), regex.find("ba"));
+	}
+}
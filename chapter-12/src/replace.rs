@@ -0,0 +1,363 @@
+// rewriting matches with a replacement template (--replace)
+//
+// in the template, $0 (or ${0}) stands for the whole match, $1 to $9 and ${N}
+// for the capturing groups of a regular expression, and $$ for a literal '$'
+// groups that didn't take part in the match are replaced with nothing
+//
+// by default the changes are only shown as a unified diff; with --in-place they
+// are written to the files, through a temporary file that is renamed over the
+// original, so a failure never leaves a half written file behind
+// a file is written back in the encoding it was read in, byte order mark included
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::decode::{Decoder, Encoding};
+use crate::matcher::Matcher;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+	Literal(String),
+	Group(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+	parts: Vec<Part>,
+}
+
+impl Template {
+	pub fn parse(template: &str) -> Template {
+		let mut parts = Vec::new();
+		let mut literal = String::new();
+		let mut rest = template;
+
+		while let Some(dollar) = rest.find('$') {
+			literal.push_str(&rest[..dollar]);
+			let after = &rest[dollar + 1..];
+
+			let (group, len) = if let Some(braced) = after.strip_prefix('{') {
+				match braced.find('}') {
+					Some(end) => (braced[..end].parse().ok(), end + 2),
+					None => (None, 0),
+				}
+			} else {
+				match after.chars().next() {
+					Some(c) if c.is_ascii_digit() => (c.to_digit(10).map(|d| d as usize), 1),
+					_ => (None, 0),
+				}
+			};
+
+			match group {
+				Some(group) => {
+					if !literal.is_empty() {
+						parts.push(Part::Literal(std::mem::take(&mut literal)));
+					}
+					parts.push(Part::Group(group));
+					rest = &after[len..];
+				}
+				None => {
+					// "$$" is a '$', and so is a '$' that doesn't start a group
+					literal.push('$');
+					rest = after.strip_prefix('$').unwrap_or(after);
+				}
+			}
+		}
+		literal.push_str(rest);
+		if !literal.is_empty() {
+			parts.push(Part::Literal(literal));
+		}
+
+		Template { parts }
+	}
+
+	fn expand(&self, line: &str, groups: &[Option<(usize, usize)>], out: &mut String) {
+		for part in &self.parts {
+			match part {
+				Part::Literal(literal) => out.push_str(literal),
+				Part::Group(group) => {
+					if let Some(Some((start, end))) = groups.get(*group) {
+						out.push_str(&line[*start..*end]);
+					}
+				}
+			}
+		}
+	}
+}
+
+// `line` with every match replaced, or None if nothing in it matches
+pub fn replace_line(matcher: &Matcher, template: &Template, line: &str) -> Option<String> {
+	let mut replaced = String::with_capacity(line.len());
+	let mut matched = false;
+	let mut copied = 0;
+	let mut position = 0;
+
+	while position <= line.len() {
+		let Some(groups) = matcher.captures_at(line, position) else {
+			break;
+		};
+		let Some((start, end)) = groups[0] else {
+			break;
+		};
+		matched = true;
+		replaced.push_str(&line[copied..start]);
+		template.expand(line, &groups, &mut replaced);
+		copied = end;
+
+		// step over empty matches so they aren't replaced forever
+		position = if start == end {
+			end + line[end..].chars().next().map_or(1, char::len_utf8)
+		} else {
+			end
+		};
+	}
+
+	if !matched {
+		return None;
+	}
+	replaced.push_str(&line[copied..]);
+	Some(replaced)
+}
+
+// a line that a replacement changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+	pub line_number: usize,
+	pub old: String,
+	// more than one line if the template has a newline
+	pub new: String,
+}
+
+// `contents` with the matches on every line replaced, along with the lines that changed
+// line terminators are kept as they are
+pub fn replace_all(
+	matcher: &Matcher,
+	template: &Template,
+	contents: &str,
+) -> (String, Vec<Change>) {
+	let mut replaced = String::with_capacity(contents.len());
+	let mut changes = Vec::new();
+
+	for (i, line) in contents.split_inclusive('\n').enumerate() {
+		let text = line.strip_suffix('\n').unwrap_or(line);
+		let text = text.strip_suffix('\r').unwrap_or(text);
+		match replace_line(matcher, template, text) {
+			Some(new) => {
+				replaced.push_str(&new);
+				replaced.push_str(&line[text.len()..]);
+				if new != text {
+					changes.push(Change {
+						line_number: i + 1,
+						old: text.to_string(),
+						new,
+					});
+				}
+			}
+			None => replaced.push_str(line),
+		}
+	}
+
+	(replaced, changes)
+}
+
+// a unified diff of the changes to the file `name`, without context lines
+pub fn diff(name: &str, changes: &[Change]) -> String {
+	let mut diff = String::new();
+	writeln!(diff, "--- a/{}\n+++ b/{}", name, name).unwrap();
+
+	// how many lines the earlier hunks added to the new file
+	let mut shift: isize = 0;
+	// consecutive changed lines form a single hunk
+	for hunk in changes.chunk_by(|a, b| a.line_number + 1 == b.line_number) {
+		let start = hunk[0].line_number;
+		let new_lines: usize = hunk
+			.iter()
+			.map(|change| change.new.split('\n').count())
+			.sum();
+		writeln!(
+			diff,
+			"@@ -{},{} +{},{} @@",
+			start,
+			hunk.len(),
+			start as isize + shift,
+			new_lines
+		)
+		.unwrap();
+		for change in hunk {
+			writeln!(diff, "-{}", change.old).unwrap();
+		}
+		for change in hunk {
+			for line in change.new.split('\n') {
+				writeln!(diff, "+{}", line).unwrap();
+			}
+		}
+		shift += new_lines as isize - hunk.len() as isize;
+	}
+
+	diff
+}
+
+// the decoded contents of a file that is rewritten, and how to encode them again
+pub struct Text {
+	pub contents: String,
+	encoding: Encoding,
+	bom: Vec<u8>,
+}
+
+impl Text {
+	// unlike searching, rewriting can't replace invalid bytes with U+FFFD, so the
+	// contents have to encode back into exactly the bytes they came from
+	pub fn decode(bytes: &[u8]) -> io::Result<Text> {
+		let (encoding, bom) = Encoding::detect(bytes);
+		let mut decoded = Vec::new();
+		Decoder::with_encoding(&bytes[bom..], encoding).read_to_end(&mut decoded)?;
+		let contents = String::from_utf8(decoded)
+			.ok()
+			.filter(|contents| {
+				encoding == Encoding::Utf8 || encoding.encode(contents) == bytes[bom..]
+			})
+			.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::InvalidData,
+					"not valid UTF-8 or UTF-16, so it can't be rewritten",
+				)
+			})?;
+		Ok(Text {
+			contents,
+			encoding,
+			bom: bytes[..bom].to_vec(),
+		})
+	}
+
+	// `contents` in the encoding the text was read in
+	pub fn encode(&self, contents: &str) -> Vec<u8> {
+		let mut bytes = self.bom.clone();
+		bytes.extend(self.encoding.encode(contents));
+		bytes
+	}
+}
+
+// replaces the contents of `path`, keeping the old ones in `path`.bak if `backup` is set
+// a symbolic link stays one, and the file it points to is replaced
+pub fn write_in_place(path: &Path, contents: &[u8], backup: bool) -> io::Result<()> {
+	if backup {
+		fs::copy(path, backup_path(path))?;
+	}
+
+	// the temporary file is in the same directory, so the rename can't cross file systems
+	let path = &fs::canonicalize(path)?;
+	let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+	let temporary = path.with_file_name(format!(".{}.minigrep-{}", file_name, process::id()));
+	let result = (|| {
+		let mut file = fs::File::create(&temporary)?;
+		file.write_all(contents)?;
+		file.sync_all()?;
+		fs::set_permissions(&temporary, fs::metadata(path)?.permissions())?;
+		fs::rename(&temporary, path)
+	})();
+	if result.is_err() {
+		let _ = fs::remove_file(&temporary);
+	}
+	result
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+	let mut backup = path.as_os_str().to_os_string();
+	backup.push(".bak");
+	PathBuf::from(backup)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::regex::Regex;
+
+	#[test]
+	fn templates() {
+		let regex = Matcher::Regex(Regex::new(r"(\w+)@(\w+)").unwrap());
+		let swap = Template::parse("$2 at ${1} ($0) costs $$5$");
+		assert_eq!(
+			Some(String::from("[host at user (user@host) costs $5$]")),
+			replace_line(&regex, &swap, "[user@host]")
+		);
+		assert_eq!(None, replace_line(&regex, &swap, "nothing"));
+
+		let literal = Matcher::literal("old");
+		let template = Template::parse("new$1");
+		assert_eq!(
+			Some(String::from("new_name = new")),
+			replace_line(&literal, &template, "old_name = old")
+		);
+	}
+
+	#[test]
+	fn replaces_and_diffs_whole_contents() {
+		let contents = "let old = 1;\r\nkeep\nold(old);\n";
+		let (replaced, changes) =
+			replace_all(&Matcher::literal("old"), &Template::parse("new"), contents);
+		assert_eq!("let new = 1;\r\nkeep\nnew(new);\n", replaced);
+		assert_eq!(
+			"--- a/f\n+++ b/f\n@@ -1,1 +1,1 @@\n-let old = 1;\n+let new = 1;\n@@ -3,1 +3,1 @@\n-old(old);\n+new(new);\n",
+			diff("f", &changes)
+		);
+
+		let (_, changes) =
+			replace_all(&Matcher::literal(";"), &Template::parse(";\n"), "a;\nb;\nc");
+		assert_eq!(
+			"--- a/f\n+++ b/f\n@@ -1,2 +1,4 @@\n-a;\n-b;\n+a;\n+\n+b;\n+\n",
+			diff("f", &changes)
+		);
+	}
+
+	#[test]
+	fn writes_in_place_with_a_backup() {
+		let path = std::env::temp_dir().join(format!("minigrep-replace-{}.txt", process::id()));
+		fs::write(&path, "before").unwrap();
+		write_in_place(&path, b"after", true).unwrap();
+
+		let contents = fs::read_to_string(&path).unwrap();
+		let backup = fs::read_to_string(backup_path(&path)).unwrap();
+		let _ = fs::remove_file(&path);
+		let _ = fs::remove_file(backup_path(&path));
+		assert_eq!(("after", "before"), (contents.as_str(), backup.as_str()));
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn writes_through_symbolic_links() {
+		let dir = std::env::temp_dir().join(format!("minigrep-link-{}", process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("target.txt"), "before").unwrap();
+		std::os::unix::fs::symlink("target.txt", dir.join("link.txt")).unwrap();
+		write_in_place(&dir.join("link.txt"), b"after", false).unwrap();
+
+		let link = fs::symlink_metadata(dir.join("link.txt")).unwrap();
+		let contents = fs::read_to_string(dir.join("target.txt")).unwrap();
+		let _ = fs::remove_dir_all(&dir);
+		assert!(link.file_type().is_symlink());
+		assert_eq!("after", contents);
+	}
+
+	#[test]
+	fn rewrites_text_in_its_encoding() {
+		let utf16: Vec<u8> = [0xFF, 0xFE]
+			.into_iter()
+			.chain("a\nb\n".encode_utf16().flat_map(u16::to_le_bytes))
+			.collect();
+		let text = Text::decode(&utf16).unwrap();
+		assert_eq!("a\nb\n", text.contents);
+		assert_eq!(utf16, text.encode("a\nb\n"));
+
+		let bom = "\u{feff}text";
+		assert_eq!(
+			bom.as_bytes(),
+			Text::decode(bom.as_bytes()).unwrap().encode("text")
+		);
+		// a lone surrogate would be written back as U+FFFD
+		assert!(Text::decode(&[0xFF, 0xFE, 0x00, 0xD8, b'a', 0]).is_err());
+		assert!(Text::decode(b"caf\xe9").is_err());
+	}
+}
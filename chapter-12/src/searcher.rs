@@ -0,0 +1,817 @@
+// searching from other programs, without going through the command line
+//
+//     let searcher = Searcher::builder()
+//         .case(Case::Smart)
+//         .word(true)
+//         .context(2)
+//         .build(&["needle"])?;
+//     searcher.search_path("haystack.txt", &mut |line: &SinkLine| {
+//         println!("{}: {}", line.line_number, line.text);
+//         Ok(true)
+//     })?;
+//
+// the search itself reads line by line, keeping only the lines that may become
+// before context, and hands every selected and context line to a Sink;
+// the printer behind the command line is one such sink
+//
+// a literal query is first looked for in everything the reader has buffered, and
+// the lines before the one it is found on are skipped without being decoded and
+// searched one by one, which is most of the input when few lines match
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::casefold;
+use crate::decode::Decoder;
+use crate::error::SearchError;
+use crate::fuzzy::Fuzzy;
+use crate::matcher::{LineMatches, Matcher};
+use crate::memmem::{self, Finder};
+use crate::multiline::{self, MultilineMatch};
+use crate::printer::BinaryFiles;
+use crate::regex::{self, Regex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Case {
+	#[default]
+	Sensitive,
+	Insensitive,
+	// insensitive unless a pattern contains an uppercase letter
+	Smart,
+}
+
+impl Case {
+	// `regex` tells whether the patterns are regular expressions, whose escapes
+	// don't count as uppercase letters
+	pub fn ignore_case<S: AsRef<str>>(self, patterns: &[S], regex: bool) -> bool {
+		let has_uppercase = match regex {
+			true => casefold::regex_has_uppercase,
+			false => casefold::has_uppercase,
+		};
+		match self {
+			Case::Sensitive => false,
+			Case::Insensitive => true,
+			Case::Smart => !patterns.iter().any(|p| has_uppercase(p.as_ref())),
+		}
+	}
+}
+
+// a selected or context line handed to a sink
+#[derive(Debug, Clone, Copy)]
+pub struct SinkLine<'a> {
+	pub line_number: usize,
+	// from the start of the input
+	pub byte_offset: usize,
+	// without its line terminator
+	pub text: &'a str,
+	found: Found<'a>,
+}
+
+// where the matches on a line come from
+#[derive(Debug, Clone, Copy)]
+enum Found<'a> {
+	// context lines and lines selected because they don't match have none
+	Nothing,
+	// searching the line on its own
+	Line(&'a Matcher),
+	// the matches of a multiline search that touch the line, whose lines are counted
+	// from `first_line` and whose spans from `start`, where the line starts
+	Multiline {
+		matches: &'a [MultilineMatch],
+		first_line: usize,
+		start: usize,
+	},
+}
+
+// the part of a match that is on a line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SinkMatch {
+	// where it starts and ends on the line
+	pub start: usize,
+	pub end: usize,
+	// the first and last line of the whole match, which are the line itself
+	// unless matches can span lines
+	pub start_line: usize,
+	pub end_line: usize,
+}
+
+impl<'a> SinkLine<'a> {
+	// every match on the line, in order
+	pub fn matches(&self) -> impl Iterator<Item = SinkMatch> + 'a {
+		let (text, line_number) = (self.text, self.line_number);
+		let line = match self.found {
+			Found::Line(matcher) => Some(LineMatches::new(matcher, text)),
+			_ => None,
+		};
+		let line = line
+			.into_iter()
+			.flatten()
+			.map(move |(start, end)| SinkMatch {
+				start,
+				end,
+				start_line: line_number,
+				end_line: line_number,
+			});
+
+		let multiline = match self.found {
+			Found::Multiline {
+				matches,
+				first_line,
+				start,
+			} => Some(matches.iter().map(move |m| SinkMatch {
+				// a match that goes on past the line ends with it
+				start: m.span.start.saturating_sub(start).min(text.len()),
+				end: m.span.end.saturating_sub(start).min(text.len()),
+				start_line: first_line + m.start_line,
+				end_line: first_line + m.end_line,
+			})),
+			_ => None,
+		};
+		line.chain(multiline.into_iter().flatten())
+	}
+}
+
+// receives the lines a search finds, in the order they are read
+// every method returns whether the search should go on
+pub trait Sink {
+	// a selected line: one that matches, or one that doesn't when inverted
+	fn matched(&mut self, line: &SinkLine) -> io::Result<bool>;
+
+	// a line before or after a selected one
+	fn context(&mut self, _line: &SinkLine) -> io::Result<bool> {
+		Ok(true)
+	}
+
+	// between two groups of lines that aren't next to each other in the input,
+	// only when there is context
+	fn context_break(&mut self) -> io::Result<bool> {
+		Ok(true)
+	}
+
+	// the input has a NUL byte from `offset` on, so it is most likely binary
+	// not called when binary inputs are searched as text
+	fn binary(&mut self, _offset: usize) -> io::Result<bool> {
+		Ok(true)
+	}
+}
+
+// so a closure can be a sink for the selected lines
+impl<F: FnMut(&SinkLine) -> io::Result<bool>> Sink for F {
+	fn matched(&mut self, line: &SinkLine) -> io::Result<bool> {
+		self(line)
+	}
+}
+
+// which lines are selected and which are context
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SearchOptions {
+	pub invert: bool,
+	pub before: usize,
+	pub after: usize,
+	pub max_count: Option<usize>,
+	pub binary_files: BinaryFiles,
+	// matches can span lines, and select every line they touch (-U)
+	pub multiline: bool,
+}
+
+// hands the lines of `reader` that `matcher` selects to `sink`, along with their context
+// the input starts after `line_number` lines and `offset` bytes
+//
+// an input is binary if its first block or any later line has a NUL byte, like grep;
+// bytes that aren't valid utf-8 are searched as U+FFFD
+//
+// returns the number of selected lines
+pub(crate) fn search_lines<R: BufRead, S: Sink>(
+	matcher: &Matcher,
+	options: &SearchOptions,
+	mut reader: R,
+	line_number: usize,
+	offset: usize,
+	sink: &mut S,
+) -> io::Result<usize> {
+	if !options.multiline {
+		return select_lines(matcher, options, reader, line_number, offset, None, sink);
+	}
+	// a match can end anywhere further down, so the whole input is searched at once
+	// it is decoded up front, so the spans of the matches point into the lines read
+	let mut contents = Vec::new();
+	reader.read_to_end(&mut contents)?;
+	let contents = String::from_utf8_lossy(&contents);
+	let matches = multiline::find_all(matcher, &contents);
+	select_lines(
+		matcher,
+		options,
+		contents.as_bytes(),
+		line_number,
+		offset,
+		Some(&matches),
+		sink,
+	)
+}
+
+// the lines that `matches` touch are the ones that match, if there are `matches`
+fn select_lines<R: BufRead, S: Sink>(
+	matcher: &Matcher,
+	options: &SearchOptions,
+	mut reader: R,
+	line_number: usize,
+	offset: usize,
+	matches: Option<&[MultilineMatch]>,
+	sink: &mut S,
+) -> io::Result<usize> {
+	let with_context = options.before > 0 || options.after > 0;
+	let max_count = options.max_count.unwrap_or(usize::MAX);
+	// lines that weren't handed out yet, in case a later match needs them as context
+	// along with their number and byte offset
+	let mut before: VecDeque<(usize, usize, String)> = VecDeque::with_capacity(options.before);
+	let mut after_left = 0;
+	let mut last_delivered: Option<usize> = None;
+	let mut count = 0;
+	let text = options.binary_files == BinaryFiles::Text;
+	let mut binary = !text && reader.fill_buf()?.contains(&0);
+	if binary && (options.binary_files == BinaryFiles::WithoutMatch || !sink.binary(offset)?) {
+		return Ok(0);
+	}
+
+	// only the lines that are neither selected nor context can be skipped
+	let finder = match matcher {
+		Matcher::Literal(query) if matches.is_none() && !options.invert => literal_finder(query),
+		_ => None,
+	};
+
+	// the offset of the next hit the finder found, so its line isn't searched again
+	let mut hit: Option<usize> = None;
+
+	let mut buffer = Vec::new();
+	// the 1-based number of the line within what `reader` reads, which is what
+	// `matches` count, and the first of `matches` that doesn't end before it
+	let mut read_lines = 0;
+	let mut next_match = 0;
+	let (first_line, first_offset) = (line_number, offset);
+	let mut line_number = line_number;
+	// the byte offset of the start of the next line
+	let mut next_offset = offset;
+	// after the last allowed match only its after context is still read
+	while count < max_count || after_left > 0 {
+		// up to a hit that was already found, there is nothing to look for
+		let looked_past = hit.is_none_or(|hit| hit < next_offset);
+		if let (Some(finder), 0, true) = (&finder, after_left, looked_past) {
+			let buffered = reader.fill_buf()?;
+			let (skip, found) = skippable(finder, buffered, options.before);
+			hit = found.map(|found| next_offset + found);
+			// a NUL byte makes the input binary, which the line that has it reports
+			if skip > 0 && (binary || text || memmem::memchr(0, &buffered[..skip]).is_none()) {
+				let lines = memmem::memcount(b'\n', &buffered[..skip]);
+				line_number += lines;
+				read_lines += lines;
+				next_offset += skip;
+				reader.consume(skip);
+				// the lines kept for context were before the skipped ones
+				before.clear();
+			}
+		}
+
+		buffer.clear();
+		let read = reader.read_until(b'\n', &mut buffer)?;
+		if read == 0 {
+			break;
+		}
+		line_number += 1;
+		read_lines += 1;
+		let offset = next_offset;
+		next_offset += read;
+		if !binary && !text && buffer.contains(&0) {
+			// binary inputs don't match at all, wherever the NUL byte turns up
+			if options.binary_files == BinaryFiles::WithoutMatch {
+				return Ok(0);
+			}
+			binary = true;
+			if !sink.binary(offset)? {
+				break;
+			}
+		}
+		let decoded = String::from_utf8_lossy(&buffer);
+		let line = decoded.strip_suffix('\n').unwrap_or(&decoded);
+		let line = line.strip_suffix('\r').unwrap_or(line);
+
+		let (is_match, found) = match matches {
+			None => {
+				let has_hit = hit.is_some_and(|hit| (offset..next_offset).contains(&hit));
+				(has_hit || matcher.is_match(line), Found::Line(matcher))
+			}
+			Some(matches) => {
+				while matches
+					.get(next_match)
+					.is_some_and(|m| m.end_line < read_lines)
+				{
+					next_match += 1;
+				}
+				let touching = matches[next_match..]
+					.iter()
+					.take_while(|m| m.start_line <= read_lines)
+					.count();
+				let found = Found::Multiline {
+					matches: &matches[next_match..next_match + touching],
+					first_line,
+					start: offset - first_offset,
+				};
+				(touching > 0, found)
+			}
+		};
+		let selected = count < max_count && is_match != options.invert;
+		if selected {
+			count += 1;
+			// a line that doesn't follow the last handed out one starts a new group
+			let first_line = before.front().map_or(line_number, |(n, _, _)| *n);
+			let new_group = last_delivered.is_some_and(|last| last + 1 < first_line);
+			if with_context && new_group && !sink.context_break()? {
+				break;
+			}
+
+			for (number, context_offset, context) in before.drain(..) {
+				let context = SinkLine {
+					line_number: number,
+					byte_offset: context_offset,
+					text: &context,
+					found: Found::Nothing,
+				};
+				if !sink.context(&context)? {
+					return Ok(count);
+				}
+			}
+			let selected = SinkLine {
+				line_number,
+				byte_offset: offset,
+				text: line,
+				found: match options.invert {
+					false => found,
+					true => Found::Nothing,
+				},
+			};
+			if !sink.matched(&selected)? {
+				break;
+			}
+			after_left = options.after;
+			last_delivered = Some(line_number);
+		} else if after_left > 0 {
+			after_left -= 1;
+			last_delivered = Some(line_number);
+			let context = SinkLine {
+				line_number,
+				byte_offset: offset,
+				text: line,
+				found: Found::Nothing,
+			};
+			if !sink.context(&context)? {
+				break;
+			}
+		} else if options.before > 0 {
+			// reuse the allocation of the line that falls out of the window
+			let mut kept = if before.len() == options.before {
+				before
+					.pop_front()
+					.map(|(_, _, kept)| kept)
+					.unwrap_or_default()
+			} else {
+				String::new()
+			};
+			kept.clear();
+			kept.push_str(line);
+			before.push_back((line_number, offset, kept));
+		}
+	}
+
+	Ok(count)
+}
+
+// the finder for a literal query, if the lines it can't match can be skipped
+fn literal_finder(query: &str) -> Option<Finder> {
+	// the query has to be found in the bytes as they are read exactly when it is in
+	// the line, which isn't so if it spans lines, reaches into the '\r' of a "\r\n"
+	// or only matches where invalid utf-8 was replaced
+	let skippable = !query.is_empty() && !query.contains(['\n', '\r', '\u{fffd}']);
+	skippable.then(|| Finder::new(query.as_bytes()))
+}
+
+// how many bytes at the start of `buffered` are whole lines that can't match,
+// less the `before` lines right in front of the first one that might, and where
+// the first hit in `buffered` is
+fn skippable(finder: &Finder, buffered: &[u8], before: usize) -> (usize, Option<usize>) {
+	let line_start = |end: usize| memmem::memrchr(b'\n', &buffered[..end]).map_or(0, |i| i + 1);
+	let hit = finder.find(buffered);
+	// without a hit, the last line may go on past the buffer and have one there
+	let mut skip = line_start(hit.unwrap_or(buffered.len()));
+	for _ in 0..before {
+		if skip == 0 {
+			break;
+		}
+		skip = line_start(skip - 1);
+	}
+	(skip, hit)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearcherBuilder {
+	case: Case,
+	regex: bool,
+	fuzzy: Option<usize>,
+	word: bool,
+	line: bool,
+	options: SearchOptions,
+}
+
+impl SearcherBuilder {
+	pub fn new() -> SearcherBuilder {
+		SearcherBuilder::default()
+	}
+
+	pub fn case(mut self, case: Case) -> SearcherBuilder {
+		self.case = case;
+		self
+	}
+
+	// treat the patterns as regular expressions instead of literal text
+	pub fn regex(mut self, regex: bool) -> SearcherBuilder {
+		self.regex = regex;
+		self
+	}
+
+	// match a literal pattern within this many edits, see the fuzzy module
+	pub fn fuzzy(mut self, max_distance: Option<usize>) -> SearcherBuilder {
+		self.fuzzy = max_distance;
+		self
+	}
+
+	// only match whole words
+	pub fn word(mut self, word: bool) -> SearcherBuilder {
+		self.word = word;
+		self
+	}
+
+	// only match whole lines, which wins over word
+	pub fn line(mut self, line: bool) -> SearcherBuilder {
+		self.line = line;
+		self
+	}
+
+	// select the lines that don't match instead
+	pub fn invert(mut self, invert: bool) -> SearcherBuilder {
+		self.options.invert = invert;
+		self
+	}
+
+	pub fn before_context(mut self, lines: usize) -> SearcherBuilder {
+		self.options.before = lines;
+		self
+	}
+
+	pub fn after_context(mut self, lines: usize) -> SearcherBuilder {
+		self.options.after = lines;
+		self
+	}
+
+	pub fn context(self, lines: usize) -> SearcherBuilder {
+		self.before_context(lines).after_context(lines)
+	}
+
+	// stop searching an input after this many selected lines
+	pub fn max_count(mut self, max_count: Option<usize>) -> SearcherBuilder {
+		self.options.max_count = max_count;
+		self
+	}
+
+	// let matches span lines, see the multiline module
+	pub fn multiline(mut self, multiline: bool) -> SearcherBuilder {
+		self.options.multiline = multiline;
+		self
+	}
+
+	pub fn binary_files(mut self, binary_files: BinaryFiles) -> SearcherBuilder {
+		self.options.binary_files = binary_files;
+		self
+	}
+
+	// compiles the patterns, any of which selects a line
+	// without patterns nothing matches, like grep with an empty pattern file
+	pub fn matcher<S: AsRef<str>>(&self, patterns: &[S]) -> Result<Matcher, SearchError> {
+		let ignore_case = self.case.ignore_case(patterns, self.regex);
+		// a literal has no other way to spell the newlines a multiline match can span
+		let unescaped: Vec<String>;
+		let patterns: Vec<&str> = match self.options.multiline && !self.regex {
+			true => {
+				unescaped = patterns
+					.iter()
+					.map(|p| multiline::unescape(p.as_ref()))
+					.collect();
+				unescaped.iter().map(String::as_str).collect()
+			}
+			false => patterns.iter().map(|p| p.as_ref()).collect(),
+		};
+
+		if patterns.is_empty() {
+			return Ok(Matcher::multi(&patterns, ignore_case));
+		}
+		if let Some(max_distance) = self.fuzzy {
+			if self.regex {
+				return Err(SearchError::invalid(
+					"--fuzzy",
+					"can't be combined with --regex",
+				));
+			}
+			let [pattern] = patterns.as_slice() else {
+				return Err(SearchError::invalid(
+					"--fuzzy",
+					"searches for a single PATTERN",
+				));
+			};
+			let fuzzy = Matcher::Fuzzy(Fuzzy::new(pattern, max_distance, ignore_case));
+			return Ok(whole(fuzzy, self.word, self.line));
+		}
+		if self.regex {
+			let options = regex::Options {
+				ignore_case,
+				whole_word: self.word,
+				whole_line: self.line,
+				multi_line: self.options.multiline,
+			};
+			// several regular expressions are searched as one alternation,
+			// after checking each on its own so errors point into the right pattern
+			let pattern = match patterns.as_slice() {
+				[pattern] => pattern.to_string(),
+				_ => {
+					for pattern in &patterns {
+						Regex::new(pattern).map_err(|source| SearchError::Pattern {
+							pattern: pattern.to_string(),
+							source,
+						})?;
+					}
+					let groups: Vec<String> =
+						patterns.iter().map(|p| format!("(?:{})", p)).collect();
+					groups.join("|")
+				}
+			};
+			let regex =
+				Regex::with_options(&pattern, options).map_err(|source| SearchError::Pattern {
+					pattern: pattern.clone(),
+					source,
+				})?;
+			return Ok(Matcher::Regex(regex));
+		}
+
+		let matcher = match patterns.as_slice() {
+			[pattern] if ignore_case => Matcher::case_insensitive(pattern),
+			[pattern] => Matcher::literal(pattern),
+			_ => Matcher::multi(&patterns, ignore_case),
+		};
+		Ok(whole(matcher, self.word, self.line))
+	}
+
+	pub fn build<S: AsRef<str>>(&self, patterns: &[S]) -> Result<Searcher, SearchError> {
+		Ok(Searcher {
+			matcher: self.matcher(patterns)?,
+			options: self.options,
+		})
+	}
+}
+
+// restricts the matches of a literal or fuzzy matcher to words or lines
+fn whole(matcher: Matcher, word: bool, line: bool) -> Matcher {
+	// like grep, -x wins over -w
+	if line {
+		Matcher::Line(Box::new(matcher))
+	} else if word {
+		Matcher::Word(Box::new(matcher))
+	} else {
+		matcher
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Searcher {
+	matcher: Matcher,
+	options: SearchOptions,
+}
+
+impl Searcher {
+	pub fn builder() -> SearcherBuilder {
+		SearcherBuilder::new()
+	}
+
+	pub fn matcher(&self) -> &Matcher {
+		&self.matcher
+	}
+
+	// each of these returns the number of selected lines
+
+	pub fn search_str<S: Sink>(&self, text: &str, sink: &mut S) -> io::Result<usize> {
+		search_lines(&self.matcher, &self.options, text.as_bytes(), 0, 0, sink)
+	}
+
+	// decodes the input like the command line does, see the decode module
+	pub fn search_reader<R: Read, S: Sink>(&self, reader: R, sink: &mut S) -> io::Result<usize> {
+		let reader = Decoder::new(BufReader::new(reader))?;
+		search_lines(&self.matcher, &self.options, reader, 0, 0, sink)
+	}
+
+	pub fn search_path<P: AsRef<Path>, S: Sink>(&self, path: P, sink: &mut S) -> io::Result<usize> {
+		self.search_reader(File::open(path)?, sink)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// records what a sink is handed, context lines marked with '-'
+	#[derive(Default)]
+	struct Record(Vec<String>);
+
+	impl Sink for Record {
+		fn matched(&mut self, line: &SinkLine) -> io::Result<bool> {
+			let spans: Vec<String> = line
+				.matches()
+				.map(|m| format!("{}..{}", m.start, m.end))
+				.collect();
+			self.0.push(format!(
+				"{}:{} {}",
+				line.line_number,
+				line.text,
+				spans.join(",")
+			));
+			Ok(true)
+		}
+
+		fn context(&mut self, line: &SinkLine) -> io::Result<bool> {
+			self.0.push(format!("{}-{}", line.line_number, line.text));
+			Ok(true)
+		}
+
+		fn context_break(&mut self) -> io::Result<bool> {
+			self.0.push(String::from("--"));
+			Ok(true)
+		}
+	}
+
+	#[test]
+	fn hands_lines_and_context_to_the_sink() {
+		let text = "Rust\nsafe, fast\nrust trust\nfour\nfive\nsix\nRUST\neight\n";
+		let searcher = Searcher::builder()
+			.case(Case::Smart)
+			.word(true)
+			.context(1)
+			.max_count(Some(2))
+			.build(&["rust"])
+			.unwrap();
+		let mut record = Record::default();
+		assert_eq!(2, searcher.search_str(text, &mut record).unwrap());
+		assert_eq!(
+			vec!["1:Rust 0..4", "2-safe, fast", "3:rust trust 0..4", "4-four"],
+			record.0
+		);
+
+		let mut record = Record::default();
+		let searcher = Searcher::builder()
+			.case(Case::Insensitive)
+			.word(true)
+			.context(1)
+			.build(&["rust"])
+			.unwrap();
+		searcher
+			.search_reader("\u{feff}rust\na\nb\nc\nRust\n".as_bytes(), &mut record)
+			.unwrap();
+		assert_eq!(
+			vec!["1:rust 0..4", "2-a", "--", "4-c", "5:Rust 0..4"],
+			record.0
+		);
+	}
+
+	#[test]
+	fn multiline_matches_select_every_line_they_touch() {
+		let text = "pub fn search(\n\tquery: &str,\n) {}\nfn other() {}\n\nfn x(\n)\n";
+		let searcher = Searcher::builder()
+			.regex(true)
+			.multiline(true)
+			.after_context(1)
+			.build(&[r"fn \w+\([^)]*\) \{\}"])
+			.unwrap();
+		let mut record = Record::default();
+		assert_eq!(4, searcher.search_str(text, &mut record).unwrap());
+		assert_eq!(
+			vec![
+				"1:pub fn search( 4..14",
+				"2:\tquery: &str, 0..13",
+				"3:) {} 0..4",
+				"4:fn other() {} 0..13",
+				"5-"
+			],
+			record.0
+		);
+
+		// every part of a match knows the lines the whole match spans
+		let mut spans = Vec::new();
+		searcher
+			.search_str(text, &mut |line: &SinkLine| {
+				spans.extend(line.matches().map(|m| (m.start_line, m.end_line)));
+				Ok(true)
+			})
+			.unwrap();
+		assert_eq!(vec![(1, 3), (1, 3), (1, 3), (4, 4)], spans);
+	}
+
+	#[test]
+	fn skipping_lines_finds_what_reading_every_line_does() {
+		let mut text = String::new();
+		for i in 0..200 {
+			text.push_str(&format!(
+				"line {} {}\n",
+				i,
+				if i % 37 == 0 { "needle" } else { "hay" }
+			));
+		}
+		text.push_str("a needle at the end without a newline");
+		let options = SearchOptions {
+			before: 2,
+			after: 1,
+			..SearchOptions::default()
+		};
+		// a tiny buffer, so lines and hits straddle its end
+		let search = |matcher: &Matcher, contents: &[u8]| {
+			let mut record = Record::default();
+			let reader = BufReader::with_capacity(16, contents);
+			let count = search_lines(matcher, &options, reader, 0, 0, &mut record).unwrap();
+			(count, record.0)
+		};
+		let regex = Matcher::Regex(Regex::new("needle").unwrap());
+		let literal = Matcher::literal("needle");
+		assert_eq!(
+			search(&regex, text.as_bytes()),
+			search(&literal, text.as_bytes())
+		);
+
+		// a NUL byte among the skipped lines still makes the input binary
+		struct Binary(Option<usize>);
+		impl Sink for Binary {
+			fn matched(&mut self, _line: &SinkLine) -> io::Result<bool> {
+				Ok(true)
+			}
+			fn binary(&mut self, offset: usize) -> io::Result<bool> {
+				self.0.get_or_insert(offset);
+				Ok(true)
+			}
+		}
+		let hay = "hay\n".repeat(20);
+		let binary = format!("{}\0hay\n{}{}", hay, hay, text);
+		let mut sink = Binary(None);
+		let reader = BufReader::with_capacity(64, binary.as_bytes());
+		search_lines(&literal, &options, reader, 0, 0, &mut sink).unwrap();
+		assert_eq!(Some(80), sink.0);
+	}
+
+	#[test]
+	fn binary_inputs_without_match_are_dropped_wherever_the_nul_is() {
+		struct Binary(bool);
+		impl Sink for Binary {
+			fn matched(&mut self, _line: &SinkLine) -> io::Result<bool> {
+				Ok(true)
+			}
+			fn binary(&mut self, _offset: usize) -> io::Result<bool> {
+				self.0 = true;
+				Ok(true)
+			}
+		}
+		let options = SearchOptions {
+			binary_files: BinaryFiles::WithoutMatch,
+			..SearchOptions::default()
+		};
+		// the NUL byte is past the first buffer
+		let contents = format!("match\n{}\0\nmatch\n", "hay\n".repeat(40));
+		let mut sink = Binary(false);
+		let reader = BufReader::with_capacity(64, contents.as_bytes());
+		let count = search_lines(
+			&Matcher::literal("match"),
+			&options,
+			reader,
+			0,
+			0,
+			&mut sink,
+		);
+		assert_eq!(0, count.unwrap());
+		assert!(!sink.0);
+	}
+
+	#[test]
+	fn closures_are_sinks_that_can_stop() {
+		let searcher = Searcher::builder().invert(true).build(&["x"]).unwrap();
+		let mut seen = Vec::new();
+		let count = searcher
+			.search_str("a\nx\nb\nc\n", &mut |line: &SinkLine| {
+				seen.push((line.line_number, line.byte_offset, line.matches().count()));
+				Ok(seen.len() < 2)
+			})
+			.unwrap();
+		assert_eq!(2, count);
+		assert_eq!(vec![(1, 0, 0), (3, 4, 0)], seen);
+	}
+}
@@ -0,0 +1,235 @@
+// recursive directory traversal for searching whole trees
+//
+// like other search tools, the walk skips hidden files and directories (the ones
+// whose name starts with a '.') and whatever the .gitignore, .ignore and
+// .minigrepignore files along the way ignore
+// on top of that, the globs and types of a Filter can narrow the files down
+//
+// a directory or entry that can't be read is recorded with its path and skipped,
+// so one unreadable directory doesn't keep the rest of the tree from being searched
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::filter::Filter;
+use crate::ignore::Ignore;
+
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+	// also search hidden files and directories
+	pub hidden: bool,
+	// don't read ignore files
+	pub no_ignore: bool,
+	// which files to search by name (-g and -t)
+	pub filter: Filter,
+}
+
+// what a walk found
+#[derive(Debug, Default)]
+pub struct Walk {
+	// the files to search, in a stable (sorted) order
+	pub files: Vec<PathBuf>,
+	// the directories and entries that couldn't be read, and why
+	pub errors: Vec<(PathBuf, io::Error)>,
+}
+
+// every file below `root` that isn't hidden, ignored or filtered out
+// `root` itself is always searched, even if it's hidden
+// symbolic links to directories are not followed, so a link cycle can't trap us
+pub fn walk(root: &Path, options: &WalkOptions) -> Walk {
+	let mut found = Walk::default();
+	let mut ignores = Vec::new();
+	visit(root, root, options, &mut ignores, &mut found);
+	found
+}
+
+// `ignores` holds the rules of `dir` and of every directory above it, outermost first
+fn visit(
+	root: &Path,
+	dir: &Path,
+	options: &WalkOptions,
+	ignores: &mut Vec<Ignore>,
+	found: &mut Walk,
+) {
+	let entries = match fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(e) => {
+			found.errors.push((dir.to_path_buf(), e));
+			return;
+		}
+	};
+	let mut entries: Vec<_> = entries
+		.filter_map(|entry| match entry {
+			Ok(entry) => Some(entry),
+			Err(e) => {
+				found.errors.push((dir.to_path_buf(), e));
+				None
+			}
+		})
+		.collect();
+	entries.sort_by_key(|entry| entry.file_name());
+
+	let own_rules = match options.no_ignore {
+		false => Ignore::read_dir(dir),
+		true => None,
+	};
+	let pushed = own_rules.is_some();
+	ignores.extend(own_rules);
+
+	for entry in entries {
+		let path = entry.path();
+		let file_type = match entry.file_type() {
+			Ok(file_type) => file_type,
+			Err(e) => {
+				found.errors.push((path, e));
+				continue;
+			}
+		};
+		let is_dir = file_type.is_dir();
+
+		if !options.hidden && entry.file_name().to_string_lossy().starts_with('.') {
+			continue;
+		}
+		if is_ignored(ignores, &path, is_dir) {
+			continue;
+		}
+
+		let relative = relative_name(root, &path);
+		if is_dir {
+			if options.filter.allows_dir(&relative) {
+				visit(root, &path, options, ignores, found);
+			}
+		} else if (file_type.is_file() || (file_type.is_symlink() && path.is_file()))
+			&& options.filter.allows_file(&relative)
+		{
+			found.files.push(path);
+		}
+	}
+
+	if pushed {
+		ignores.pop();
+	}
+}
+
+// the rules of the deepest directory that mention `path` decide, like with git
+fn is_ignored(ignores: &[Ignore], path: &Path, is_dir: bool) -> bool {
+	ignores
+		.iter()
+		.rev()
+		.find_map(|ignore| ignore.matched(path, is_dir))
+		.unwrap_or(false)
+}
+
+// `path` relative to `root`, with '/' separators
+pub fn relative_name(root: &Path, path: &Path) -> String {
+	let relative = path.strip_prefix(root).unwrap_or(path);
+	let components: Vec<_> = relative
+		.components()
+		.map(|c| c.as_os_str().to_string_lossy())
+		.collect();
+	components.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn walks_nested_directories_in_order() {
+		let root = std::env::temp_dir().join(format!("minigrep-walk-{}", std::process::id()));
+		fs::create_dir_all(root.join("b/c")).unwrap();
+		fs::write(root.join("b/c/deep.txt"), "deep").unwrap();
+		fs::write(root.join("b/file.txt"), "file").unwrap();
+		fs::write(root.join("a.txt"), "a").unwrap();
+
+		let files = walk(&root, &WalkOptions::default()).files;
+		let mut filtered = WalkOptions::default();
+		filtered.filter.add_glob("!c").unwrap();
+		filtered.filter.add_type("txt").unwrap();
+		let filtered = walk(&root, &filtered).files;
+		let _ = fs::remove_dir_all(&root);
+
+		let names: Vec<_> = files
+			.iter()
+			.map(|f| f.strip_prefix(&root).unwrap().to_path_buf())
+			.collect();
+		assert_eq!(
+			vec![
+				PathBuf::from("a.txt"),
+				PathBuf::from("b/c/deep.txt"),
+				PathBuf::from("b/file.txt")
+			],
+			names
+		);
+		assert_eq!(vec![root.join("a.txt"), root.join("b/file.txt")], filtered);
+	}
+
+	#[test]
+	fn skips_hidden_and_ignored_files() {
+		let root = std::env::temp_dir().join(format!("minigrep-ignore-{}", std::process::id()));
+		fs::create_dir_all(root.join("target/debug")).unwrap();
+		fs::create_dir_all(root.join("src/.cache")).unwrap();
+		fs::write(root.join(".gitignore"), "/target\n*.log\n").unwrap();
+		fs::write(root.join("src/.ignore"), "!keep.log\n").unwrap();
+		fs::write(root.join("target/debug/out.txt"), "out").unwrap();
+		fs::write(root.join("src/.cache/hidden.txt"), "hidden").unwrap();
+		fs::write(root.join("src/main.rs"), "main").unwrap();
+		fs::write(root.join("src/debug.log"), "debug").unwrap();
+		fs::write(root.join("src/keep.log"), "keep").unwrap();
+
+		let relative = |files: Vec<PathBuf>| -> Vec<PathBuf> {
+			files
+				.iter()
+				.map(|f| f.strip_prefix(&root).unwrap().to_path_buf())
+				.collect()
+		};
+		let default = relative(walk(&root, &WalkOptions::default()).files);
+		let everything = WalkOptions {
+			hidden: true,
+			no_ignore: true,
+			..WalkOptions::default()
+		};
+		let all = relative(walk(&root, &everything).files);
+		let _ = fs::remove_dir_all(&root);
+
+		assert_eq!(
+			vec![PathBuf::from("src/keep.log"), PathBuf::from("src/main.rs")],
+			default
+		);
+		assert_eq!(7, all.len());
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn reports_unreadable_directories_and_keeps_walking() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let root = std::env::temp_dir().join(format!("minigrep-locked-{}", std::process::id()));
+		fs::create_dir_all(root.join("locked")).unwrap();
+		fs::write(root.join("locked/secret.txt"), "secret").unwrap();
+		fs::write(root.join("open.txt"), "open").unwrap();
+		fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+		let readable = fs::read_dir(root.join("locked")).is_ok();
+		let found = walk(&root, &WalkOptions::default());
+		fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+		let _ = fs::remove_dir_all(&root);
+
+		if readable {
+			// root reads the directory anyway, so there is nothing to report
+			eprintln!("skipped: the locked directory can be read, running as root?");
+			return;
+		}
+		assert_eq!(vec![root.join("open.txt")], found.files);
+		assert_eq!(root.join("locked"), found.errors[0].0);
+	}
+
+	#[test]
+	fn reports_a_missing_root() {
+		let missing = std::env::temp_dir().join(format!("minigrep-missing-{}", std::process::id()));
+		let walked = walk(&missing, &WalkOptions::default());
+		assert!(walked.files.is_empty());
+		assert_eq!(missing, walked.errors[0].0);
+		assert_eq!(io::ErrorKind::NotFound, walked.errors[0].1.kind());
+	}
+}
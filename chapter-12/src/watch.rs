@@ -0,0 +1,228 @@
+// following files as they change, for --watch
+//
+// like tail -f, a file is expected to grow: the watcher remembers how far each file
+// was read and only hands out the lines appended since
+// a file that got shorter, or whose last line that was handed out changed, was
+// truncated or rewritten (say, by log rotation), so it is read again from the start
+// a last line without its newline is still being written, and waits for it
+//
+// files are read as they are searched, through a buffer, so following a large file
+// doesn't load it into memory
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Take};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::decode::{Decoder, Encoding};
+use crate::memmem;
+
+// how many bytes before the end of what was read are compared, to notice a rewrite
+const TAIL: u64 = 64;
+
+// how far a file was read
+#[derive(Debug, Clone, Default)]
+struct Tracked {
+	len: u64,
+	modified: Option<SystemTime>,
+	// the end of the last complete line that was handed out
+	offset: u64,
+	lines: usize,
+	// the bytes right before `offset`
+	tail: Vec<u8>,
+	// what the start of the file announced
+	encoding: Encoding,
+}
+
+// the lines that appeared in a file since the last poll
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+	// where the lines start in the file, as the number of lines and bytes before them
+	pub line_number: usize,
+	pub offset: usize,
+	// the bytes of the complete lines, each with its newline
+	pub len: u64,
+	encoding: Encoding,
+}
+
+impl Update {
+	// reads the lines of the update from `path`, decoded to utf-8
+	pub fn reader(&self, path: &Path) -> io::Result<Decoder<BufReader<Take<File>>>> {
+		let mut file = File::open(path)?;
+		file.seek(SeekFrom::Start(self.offset as u64))?;
+		let reader = BufReader::new(file.take(self.len));
+		if self.offset == 0 {
+			// drops the byte order mark
+			Decoder::new(reader)
+		} else {
+			Ok(Decoder::with_encoding(reader, self.encoding))
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct Watcher {
+	files: HashMap<PathBuf, Tracked>,
+}
+
+impl Watcher {
+	pub fn new() -> Watcher {
+		Watcher::default()
+	}
+
+	// starts following `path` from its current end, so what is in it now isn't reported
+	pub fn track(&mut self, path: &Path) -> io::Result<()> {
+		let mut tracked = Tracked::default();
+		read_new(path, &mut tracked)?;
+		self.files.insert(path.to_path_buf(), tracked);
+		Ok(())
+	}
+
+	// the lines added to `path` since it was last tracked or polled, if there are any
+	// a file that isn't tracked yet is new, and all of it is reported
+	pub fn poll(&mut self, path: &Path) -> io::Result<Option<Update>> {
+		let tracked = self.files.entry(path.to_path_buf()).or_default();
+		read_new(path, tracked)
+	}
+}
+
+fn read_new(path: &Path, tracked: &mut Tracked) -> io::Result<Option<Update>> {
+	let metadata = fs::metadata(path)?;
+	let (len, modified) = (metadata.len(), metadata.modified().ok());
+	if len == tracked.len && modified == tracked.modified {
+		return Ok(None);
+	}
+
+	let mut file = File::open(path)?;
+	if len < tracked.offset || tail(&mut file, tracked.offset)? != tracked.tail {
+		*tracked = Tracked::default();
+	}
+	tracked.len = len;
+	tracked.modified = modified;
+	if tracked.offset == 0 {
+		let mut start = Vec::new();
+		(&mut file).take(3).read_to_end(&mut start)?;
+		tracked.encoding = Encoding::detect(&start).0;
+	}
+
+	file.seek(SeekFrom::Start(tracked.offset))?;
+	let reader = BufReader::new((&mut file).take(len - tracked.offset));
+	let (complete, lines) = complete_lines(reader, tracked.encoding)?;
+	if complete == 0 {
+		return Ok(None);
+	}
+
+	let update = Update {
+		line_number: tracked.lines,
+		offset: tracked.offset as usize,
+		len: complete,
+		encoding: tracked.encoding,
+	};
+	tracked.offset += complete;
+	tracked.lines += lines;
+	tracked.tail = tail(&mut file, tracked.offset)?;
+	Ok(Some(update))
+}
+
+// the bytes of `file` right before `end`
+fn tail(file: &mut File, end: u64) -> io::Result<Vec<u8>> {
+	let start = end.saturating_sub(TAIL);
+	file.seek(SeekFrom::Start(start))?;
+	let mut tail = vec![0; (end - start) as usize];
+	file.read_exact(&mut tail)?;
+	Ok(tail)
+}
+
+// how many bytes of `reader` are complete lines, and how many lines that is
+fn complete_lines<R: BufRead>(mut reader: R, encoding: Encoding) -> io::Result<(u64, usize)> {
+	let newline = encoding.newline();
+	let (mut read, mut complete, mut lines) = (0, 0, 0);
+	// a utf-16 unit split between two reads
+	let mut split = None;
+	loop {
+		let buffered = reader.fill_buf()?;
+		if buffered.is_empty() {
+			return Ok((complete, lines));
+		}
+		let chunk = buffered.len();
+		if encoding == Encoding::Utf8 {
+			if let Some(last) = memmem::memrchr(b'\n', buffered) {
+				complete = read + last as u64 + 1;
+				lines += memmem::memcount(b'\n', buffered);
+			}
+		} else {
+			let mut units = buffered;
+			if let Some(first) = split.take() {
+				if [first, units[0]] == newline {
+					complete = read + 1;
+					lines += 1;
+				}
+				units = &units[1..];
+			}
+			let skipped = chunk - units.len();
+			for (i, unit) in units.chunks(2).enumerate() {
+				if unit == newline {
+					complete = read + (skipped + i * 2 + 2) as u64;
+					lines += 1;
+				} else if unit.len() == 1 {
+					split = Some(unit[0]);
+				}
+			}
+		}
+		read += chunk as u64;
+		reader.consume(chunk);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+
+	#[test]
+	fn reports_appended_lines() {
+		let path = std::env::temp_dir().join(format!("minigrep-watch-{}.log", std::process::id()));
+		fs::write(&path, "old 1\nold 2\n").unwrap();
+		let append = |bytes: &[u8]| {
+			let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+			file.write_all(bytes).unwrap();
+		};
+		let mut watcher = Watcher::new();
+		watcher.track(&path).unwrap();
+		// the lines are read before the file changes again
+		let mut poll = || {
+			watcher.poll(&path).unwrap().map(|update| {
+				let mut text = String::new();
+				update
+					.reader(&path)
+					.unwrap()
+					.read_to_string(&mut text)
+					.unwrap();
+				(text, update.line_number, update.offset)
+			})
+		};
+		let update =
+			|text: &str, line_number, offset| Some((text.to_string(), line_number, offset));
+
+		assert_eq!(None, poll());
+		append(b"new 3\nnew ");
+		assert_eq!(update("new 3\n", 2, 12), poll());
+		append(b"4\n");
+		assert_eq!(update("new 4\n", 3, 18), poll());
+		fs::write(&path, "rotated\n").unwrap();
+		assert_eq!(update("rotated\n", 0, 0), poll());
+		// rewritten into something longer, so only what was read changed
+		fs::write(&path, "rewritten 1\nrewritten 2\n").unwrap();
+		assert_eq!(update("rewritten 1\nrewritten 2\n", 0, 0), poll());
+
+		// utf-16 is decoded, and a newline is a whole unit
+		let utf16 =
+			|text: &str| -> Vec<u8> { text.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+		fs::write(&path, [&[0xFF, 0xFE][..], &utf16("\u{a0a}\n")].concat()).unwrap();
+		assert_eq!(update("\u{a0a}\n", 0, 0), poll());
+		append(&utf16("b\nc"));
+		assert_eq!(update("b\n", 1, 6), poll());
+		let _ = fs::remove_file(&path);
+	}
+}